@@ -40,6 +40,14 @@ fn main_of_main() {
                     "wry://"
                 },
             )
+            .replace(
+                "__FW_SCHEME__",
+                if cfg!(target_os = "windows") {
+                    "https://fw."
+                } else {
+                    "fw://"
+                },
+            )
             .as_bytes(),
     )
     .unwrap();
@@ -70,16 +70,53 @@ export function request(method, endpoint, body, callback, isResponseJson=false,
 };
 
 
+/**
+ * Make a URL to a file of the app, a wallpaper profile, an extension or an allowed folder.
+ * Use this instead of absolute paths so that profiles keep working when the setting folder moves.
+ * @param {string} kind - `app` / `wallpaper` / `extension` / `file`
+ * @param {string} path - Path in it. Example: `MyWallpaper/image.png` for `wallpaper`.
+ * @returns {string} - URL like `fw://wallpaper/MyWallpaper/image.png`
+ */
+export function assetUrl(kind, path) {
+    return `__FW_SCHEME__${kind}/${encodeURI(path.replace(/\\/g, "/").replace(/^\/+/, ""))}`;
+};
+
+
 /**
  * Open file dialog
  * @param {function} callback - Callback to be passed path
+ * @param {boolean} folder - Whether to choose a folder instead of a file
  */
-export function open(callback) {
-    request(POST, "open/.../...", "", SILENT, false, false);
+export function open(callback, folder=false) {
+    request(POST, `open/${folder ? "folder" : "..."}/...`, "", SILENT, false, false);
     window._fileSelected = callback;
 };
 
 
+/**
+ * Ask the user with a native dialog whether to continue.
+ * Use this instead of `window.confirm`, which does not work in the webview on Mac.
+ * @param {string} kind - `approveAsset` / `restoreSnapshot` / `importArchive` / `removeTrash` / `emptyTrash` (The message is chosen from this and shown in the user's language.)
+ * @param {string} text - Path or name put into the message
+ * @param {function} callback - Callback to be passed whether the user accepted
+ */
+export function confirmDialog(kind, text, callback) {
+    request(POST, `confirm/${kind}/...`, text, result => callback(Boolean(Number(result))), false, false);
+};
+
+
+/**
+ * Show a message to the user with a native dialog.
+ * Use this instead of `window.alert`, which does not work in the webview on Mac.
+ * @param {string} kind - `exported` / `loadJsonFailed` (The message is chosen from this and shown in the user's language.)
+ * @param {string} text - Path or name put into the message
+ * @param {function} callback - Callback to be called when the dialog is closed
+ */
+export function alertDialog(kind, text, callback=SILENT) {
+    request(POST, `alert/${kind}/...`, text, _ => callback(), false, false);
+};
+
+
 /**
  * Open folder
  * @param {string} path - Path to folder
@@ -106,4 +143,14 @@ export function openFolder(path, callback) {
  */
 export function getPath(callback) {
     request(POST, "getPath/.../...", "", callback);
+};
+
+
+/**
+ * Get the files in a folder. The folder has to be one that the page is allowed to read files from.
+ * @param {string} path - Path to folder (Relative paths are from the folder of the wallpaper profile.)
+ * @param {function} callback - Callback to be passed a list of the absolute paths of the files
+ */
+export function listFiles(path, callback) {
+    request(POST, "files/list/get", path, callback, true, false);
 };
\ No newline at end of file
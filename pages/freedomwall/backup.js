@@ -0,0 +1,97 @@
+//! FreedomWall.js - Backup
+
+import { request, SILENT, POST } from "./utils.js";
+
+
+/*
+ * Snapshots of the configuration (general settings, `data.json` of wallpaper profiles and extensions, and storages).
+ * Files of wallpaper profiles and extensions such as images and videos are not included.
+ * A snapshot is created automatically before destructive operations such as removing a wallpaper profile,
+ * at most once every 10 minutes for the same reason.
+ * A snapshot is in the following format:
+ * ```js
+ * {
+ *     "id": "1700000000-remove", // ID of the snapshot
+ *     "createdAt": 1700000000, // UNIX time when it was created
+ *     "reason": "remove", // `manual`, `wallpapers`, `remove`, `extension`, `scene` or `restore`
+ *     "size": 1024 // Size of the file in bytes
+ * }
+ * ```
+ */
+
+
+/**
+ * Get the snapshots from the newest one.
+ * @param {function} callback - Callback to be passed a list of snapshots
+ */
+export function getSnapshots(callback) {
+    request(POST, "backup/snapshots/get", "", callback, true);
+};
+
+
+/**
+ * Create a snapshot of the current configuration.
+ * @param {function} callback - Callback to be passed the created snapshot
+ */
+export function createSnapshot(callback=SILENT) {
+    request(POST, "backup/snapshots/update", "", callback, true);
+};
+
+
+/**
+ * Restore the configuration of a snapshot.
+ * Only wallpaper profiles and extensions that still exist get their `data.json` back.
+ * A snapshot of the current configuration is created before restoring, and it is rolled back if it cannot be loaded.
+ * @param {string} id - ID of the snapshot
+ */
+export function restoreSnapshot(id, reload=true) {
+    request(POST, `backup/snapshots/update/${encodeURIComponent(id)}/restore`, "", SILENT, false, reload);
+};
+
+
+/**
+ * Remove a snapshot.
+ * @param {string} id - ID of the snapshot
+ */
+export function removeSnapshot(id, reload=true) {
+    request(POST, `backup/snapshots/update/${encodeURIComponent(id)}/remove`, "", SILENT, false, reload);
+};
+
+
+/**
+ * Get how many and how long snapshots are kept.
+ * @param {function} callback - Callback to be passed `{"count": 20, "days": 30}` (`0` means unlimited)
+ */
+export function getRetention(callback) {
+    request(POST, "backup/retention/get", "", callback, true);
+};
+
+
+/**
+ * Set how many and how long snapshots are kept. Old snapshots are removed immediately.
+ * @param {object} retention - `{"count": 20, "days": 30}` (`0` means unlimited)
+ */
+export function postRetention(retention, reload=true) {
+    request(POST, "backup/retention/update", retention, SILENT, false, reload);
+};
+
+
+/**
+ * Export the whole configuration as a ZIP file.
+ * @param {string} path - Path of the ZIP file to be created
+ * @param {function} callback - Callback to be called
+ */
+export function exportConfiguration(path, callback=SILENT) {
+    request(POST, "backup/archive/update/export", path, _ => callback(), false, false);
+};
+
+
+/**
+ * Replace the whole configuration with an exported ZIP file.
+ * The file is checked before anything is replaced, a snapshot of the current configuration is created before importing,
+ * and it is rolled back if it cannot be loaded.
+ * @param {string} path - Path of the ZIP file
+ */
+export function importConfiguration(path, reload=true) {
+    request(POST, "backup/archive/update/import", path, SILENT, false, reload);
+};
\ No newline at end of file
@@ -10,10 +10,15 @@ import { POST, request } from "./utils.js";
  * {
  *     "description": "Extension description",
  *     "author": "Extension author",
- *     "version": "Extension version",
+ *     "version": "1.0.0", // Extension version (Semantic Versioning)
  *     "setting": {
  *         "Key": "Value" // Extension's setting
- *     }
+ *     },
+ *     "capabilities": [], // Extra API permissions for the wallpaper pages the extension runs in. (e.g. `settingRead`)
+ *     "min_app_version": null, // Minimum version of FreedomWall. (e.g. `2.1.0`)
+ *     "dependencies": {}, // Names of the extensions this extension needs and their version requirements. (e.g. `{"Base": "^1.0"}`)
+ *     "entry_points": [], // Scripts to be loaded into wallpapers. `init.js` is used if empty.
+ *     "load_order": 0 // Extensions with a smaller value are loaded first. Dependencies are always loaded before.
  * }
  * ```
  * @param {function} callback - Callback to be passed data (`{"ExtensionName": Above data}`)
@@ -44,6 +49,69 @@ export function updateExtensionSetting(name, data, callback) {
 };
 
 
+/**
+ * Get whether each extension is enabled.
+ * @param {function} callback - Callback to be passed data (`{"ExtensionName": true}`)
+ */
+export function getEnabledExtensions(callback) {
+    request(POST, "extensions/enabled/get", "", callback, true);
+};
+
+
+/**
+ * Enable or disable extensions.
+ * Disabled extensions are not loaded into any wallpaper.
+ * @param {object} data - `{"ExtensionName": false}`
+ * @param {function} callback - Callback to be called
+ */
+export function postEnabledExtensions(data, callback) {
+    request(POST, "extensions/enabled/update", JSON.stringify(data), _ => callback());
+};
+
+
+/**
+ * Get extensions which were not loaded.
+ * For example, an extension needs a newer FreedomWall or its dependency is missing.
+ * @param {function} callback - Callback to be passed data (`{"ExtensionName": "Reason"}`)
+ */
+export function getRejectedExtensions(callback) {
+    request(POST, "extensions/rejected/get", "", callback, true);
+};
+
+
+/**
+ * Install an extension.
+ * If the extension requests capabilities, the user is asked to allow them with a native dialog.
+ * @param {string} path - Path to the extension folder, its `data.json` or a zip file.
+ * @param {function} callback - Callback to be passed the name of the installed extension.
+ */
+export function installExtension(path, callback) {
+    request(POST, "extensions/all/update", path, callback);
+};
+
+
+/**
+ * Upgrade an extension with a new version.
+ * The current setting values are kept.
+ * @param {string} name - Extension name
+ * @param {string} path - Path to the new version's folder, its `data.json` or a zip file.
+ * @param {function} callback - Callback to be called
+ */
+export function upgradeExtension(name, path, callback) {
+    request(POST, `extensions/one/update/${name}/upgrade`, path, _ => callback());
+};
+
+
+/**
+ * Uninstall an extension.
+ * @param {string} name - Extension name
+ * @param {function} callback - Callback to be called
+ */
+export function uninstallExtension(name, callback) {
+    request(POST, `extensions/one/update/${name}/remove`, "", _ => callback());
+};
+
+
 /**
  * Reload extensions
  */
@@ -1,6 +1,6 @@
 //! FreedomWall.js - General
 
-import { request, SILENT, POST } from "./utils.js";
+import { request, confirmDialog, SILENT, POST } from "./utils.js";
 
 
 /**
@@ -26,10 +26,28 @@ export function getLanguage(callback) {
  * Wallpaper setting data is in the following format
  * ```js
  * {
+ *     "id": "", // ID of the setting. Keep it when editing. It is assigned automatically if empty.
+ *     "enabled": true, // Whether to use this setting. The setting is kept even if disabled.
  *     "targets": [], // List of strings included in the name of the application to which the wallpaper will be attached
  *     "exceptions": [], // List of strings included in the name of the application to which the wallpaper will be not attached.
- *     "alpha": 0.2, // Transparency level
- *     "wallpaper": "" // The name of the wallpaper to be attached.
+ *     "alpha": 0.2, // Transparency level while the target window is focused
+ *     "unfocused_alpha": null, // Transparency level while the target window is not focused. `null` means the same as `alpha`.
+ *     "hide_unfocused": false, // Whether to hide the wallpaper while the target window is not focused
+ *     "fade": 0, // Seconds to change the transparency level when the focus changes
+ *     "wallpaper": "", // The name of the wallpaper to be attached.
+ *     "extensions": null, // Names of the extensions to be loaded. `null` means following the wallpaper profile.
+ *     "playlist": { // Wallpaper profiles to be switched over time. `null` means using only `wallpaper`.
+ *         "wallpapers": [], // Names of the wallpaper profiles
+ *         "interval": 600, // Seconds until the next wallpaper profile
+ *         "order": "sequential", // `sequential` / `shuffle`
+ *         "transition": 1.0 // Seconds of the fade when switching
+ *     },
+ *     "schedule": { // When this setting is used. `null` means always. Otherwise the next matching setting is used.
+ *         "times": [{"start": "sunrise+30", "end": "19:00"}], // `HH:MM`, `sunrise±minutes` or `sunset±minutes`
+ *         "weekdays": [1, 2, 3, 4, 5], // `0` is Sunday
+ *         "dates": [{"start": "12-01", "end": "12-25"}] // `MM-DD` or `YYYY-MM-DD`
+ *     },
+ *     "setting": {} // Values overriding the settings of the wallpaper profile only for this setting (e.g. `{"imagePath": "..."}`)
  * }
  * ```
  * @param {list} wallpapers - This is the list that contains the objects above.
@@ -94,4 +112,188 @@ export function postDev(onoff) {
  */
 export function getDev(callback) {
     request(POST, "setting/dev/get", "", text => callback(Boolean(Number(text))));
+};
+
+
+/**
+ * Get the name of the scene in use.
+ * A scene is a named list of wallpaper settings. `postWallpapers` and `getWallpapers` use the scene in use.
+ * @param {function} callback - Callback will be passed the name.
+ */
+export function getScene(callback) {
+    request(POST, "setting/scene/get", "", callback);
+};
+
+
+/**
+ * Switch to another scene.
+ * Wallpapers which stay the same in the new scene are kept without being recreated.
+ * @param {string} name - Name of the scene
+ */
+export function postScene(name, reload=true) {
+    request(POST, "setting/scene/update", name, SILENT, false, reload);
+};
+
+
+/**
+ * Get the names of all scenes including the scene in use.
+ * @param {function} callback - Callback will be passed a list of the names.
+ */
+export function getScenes(callback) {
+    request(POST, "setting/scenes/get", "", callback, true);
+};
+
+
+/**
+ * Get the wallpaper settings of a scene.
+ * @param {string} name - Name of the scene
+ * @param {function} callback - Callback will be passed a list of wallpaper settings. (See `postWallpapers`)
+ */
+export function getSceneWallpapers(name, callback) {
+    request(POST, `setting/scenes/get/${encodeURIComponent(name)}`, "", callback, true);
+};
+
+
+/**
+ * Create or update a scene.
+ * @param {string} name - Name of the scene
+ * @param {list} wallpapers - List of wallpaper settings (See `postWallpapers`) or `null` to copy the scene in use
+ */
+export function postSceneWallpapers(name, wallpapers, reload=true) {
+    request(
+        POST, `setting/scenes/update/${encodeURIComponent(name)}`,
+        wallpapers ?? "", SILENT, false, reload
+    );
+};
+
+
+/**
+ * Remove a scene. The scene in use cannot be removed.
+ * @param {string} name - Name of the scene
+ */
+export function deleteScene(name, reload=true) {
+    request(POST, `setting/scenes/update/${encodeURIComponent(name)}/remove`, "", SILENT, false, reload);
+};
+
+
+/**
+ * Get the hotkeys to switch scenes.
+ * @param {function} callback - Callback will be passed `{"Scene name": "Ctrl+Shift+1"}`.
+ */
+export function getSceneHotkeys(callback) {
+    request(POST, "setting/sceneHotkeys/get", "", callback, true);
+};
+
+
+/**
+ * Set the hotkeys to switch scenes.
+ * @param {object} hotkeys - `{"Scene name": "Ctrl+Shift+1"}`
+ */
+export function postSceneHotkeys(hotkeys, reload=true) {
+    request(POST, "setting/sceneHotkeys/update", hotkeys, SILENT, false, reload);
+};
+
+
+/**
+ * Stop or resume all wallpapers. The wallpaper settings are kept while stopped.
+ * @param {boolean} onoff - Whether to stop
+ */
+export function postPaused(onoff) {
+    request(POST, "setting/paused/update", Number(onoff).toString(), SILENT);
+};
+
+
+/**
+ * Get whether all wallpapers are stopped.
+ * @param {function} callback - Callback will be passed whether they are stopped or not.
+ */
+export function getPaused(callback) {
+    request(POST, "setting/paused/get", "", text => callback(Boolean(Number(text))));
+};
+
+
+/**
+ * Enable or disable a wallpaper setting without removing it.
+ * @param {string} id - ID of the wallpaper setting
+ * @param {boolean} onoff - Whether to enable
+ */
+export function postEnabled(id, onoff) {
+    request(POST, `setting/enabled/update/${encodeURIComponent(id)}`, Number(onoff).toString(), SILENT, false, false);
+};
+
+
+/**
+ * Set whether to pause all wallpapers while a fullscreen app is in front.
+ * @param {boolean} onoff
+ */
+export function postPauseOnFullscreen(onoff) {
+    request(POST, "setting/pauseOnFullscreen/update", Number(onoff).toString(), SILENT);
+};
+
+
+/**
+ * Get whether to pause all wallpapers while a fullscreen app is in front.
+ * @param {function} callback - Callback will be passed whether it is enabled or not.
+ */
+export function getPauseOnFullscreen(callback) {
+    request(POST, "setting/pauseOnFullscreen/get", "", text => callback(Boolean(Number(text))));
+};
+
+
+/**
+ * Get the files and folders that wallpapers and extensions are allowed to read.
+ * @param {function} callback - Callback will be passed a list of paths.
+ */
+export function getAssets(callback) {
+    request(POST, "setting/assets/get", "", callback, true);
+};
+
+
+/**
+ * Update the files and folders that wallpapers and extensions are allowed to read.
+ * Filesystem roots, the home folder and folders above it cannot be allowed.
+ * @param {list} assets - List of paths
+ */
+export function postAssets(assets, reload=true) {
+    request(POST, "setting/assets/update", assets, SILENT, false, reload);
+};
+
+
+/**
+ * Ask the user whether wallpapers may read a selected file or folder, and allow only that one if confirmed.
+ * Nothing is asked if it is already allowed.
+ * @param {string} path - Path of the file or folder
+ * @param {function} callback - Callback will be passed whether it is allowed.
+ */
+export function approveAsset(path, callback=SILENT) {
+    getAssets(assets => {
+        let normalize = x => x.replace(/\\/g, "/").replace(/\/+$/, "");
+        if (assets.some(x => normalize(path) == normalize(x) || normalize(path).startsWith(`${normalize(x)}/`)))
+            return callback(true);
+        confirmDialog("approveAsset", path, accepted => {
+            if (accepted) {
+                assets.push(path);
+                postAssets(assets, false);
+            };
+            callback(accepted);
+        });
+    });
+};
+
+
+/**
+ * Get the location used to calculate sunrise and sunset for schedules.
+ * @param {function} callback - Callback will be passed `{"latitude": 35.68, "longitude": 139.76}` or `null` if not set.
+ */
+export function getLocation(callback) {
+    request(POST, "setting/location/get", "", callback, true);
+};
+
+
+/**
+ * Set the location used to calculate sunrise and sunset for schedules.
+ * @param {object} location - `{"latitude": 35.68, "longitude": 139.76}` or `null`
+ */
+export function postLocation(location, reload=true) {
+    request(POST, "setting/location/update", location, SILENT, false, reload);
 };
\ No newline at end of file
@@ -0,0 +1,65 @@
+//! FreedomWall.js - Storage
+
+import { request, SILENT, POST } from "./utils.js";
+
+
+/*
+ * Each wallpaper profile and extension has its own storage which is kept after restart.
+ * `kind` is `wallpaper` or `extension` and `name` is the name of the wallpaper profile or the extension.
+ * Wallpaper pages can only use the storage of themselves and of the extensions loaded into them.
+ * A storage cannot be larger than 1MB.
+ */
+
+
+/**
+ * Get all items in the storage.
+ * @param {string} kind - `wallpaper` / `extension`
+ * @param {string} name - Wallpaper profile name or extension name
+ * @param {function} callback - Callback to be passed data (`{"Key": "Value"}`)
+ */
+export function getStorage(kind, name, callback) {
+    request(POST, `storage/${kind}/get/${name}`, "", callback, true);
+};
+
+
+/**
+ * Get an item in the storage.
+ * @param {string} kind - `wallpaper` / `extension`
+ * @param {string} name - Wallpaper profile name or extension name
+ * @param {string} key - Key
+ * @param {function} callback - Callback to be passed the value
+ */
+export function getStorageItem(kind, name, key, callback) {
+    request(POST, `storage/${kind}/get/${name}/${encodeURIComponent(key)}`, "", callback);
+};
+
+
+/**
+ * Save an item in the storage.
+ * @param {string} kind - `wallpaper` / `extension`
+ * @param {string} name - Wallpaper profile name or extension name
+ * @param {string} key - Key
+ * @param {string} value - Value
+ * @param {function} callback - Callback to be called
+ */
+export function setStorageItem(kind, name, key, value, callback=SILENT) {
+    request(
+        POST, `storage/${kind}/update/${name}/${encodeURIComponent(key)}`,
+        value, _ => callback(), false, false
+    );
+};
+
+
+/**
+ * Delete an item in the storage.
+ * @param {string} kind - `wallpaper` / `extension`
+ * @param {string} name - Wallpaper profile name or extension name
+ * @param {string} key - Key
+ * @param {function} callback - Callback to be called
+ */
+export function deleteStorageItem(kind, name, key, callback=SILENT) {
+    request(
+        POST, `storage/${kind}/update/${name}/${encodeURIComponent(key)}/delete`,
+        "", _ => callback(), false, false
+    );
+};
\ No newline at end of file
@@ -0,0 +1,88 @@
+//! FreedomWall.js - Target
+//! These are for wallpaper pages.
+
+
+/**
+ * Get the state of the window to which the wallpaper is attached.
+ * The state is in the following format:
+ * ```js
+ * {
+ *     "title": "Discord", // Window title (Application name on Mac)
+ *     "process": "Discord.exe", // Name of the process of the window
+ *     "focused": true, // Whether the window is the frontmost window
+ *     "rect": {"x": 0, "y": 0, "width": 800, "height": 600}, // Position and size on the screen
+ *     "monitor": {"index": 0, "name": "..."}, // Monitor on which the window is. (`null` if unknown)
+ *     "zOrder": 0, // Order of the window from the front (`0` is the frontmost)
+ *     "visibility": { // Whether the window is minimized, maximized, fullscreen, on another virtual desktop or hidden by the OS (`minimized` and `cloaked` are Windows only; minimized windows on Mac are reported as `otherWorkspace`)
+ *         "minimized": false, "maximized": false, "fullscreen": false,
+ *         "otherWorkspace": false, "cloaked": false
+ *     }
+ * }
+ * ```
+ * @returns {object} - The state above
+ */
+export function getTarget() {
+    return window.freedomWall.target;
+};
+
+
+/**
+ * Register a callback to be called when the state of the window changes.
+ * @param {function} callback - Callback to be passed the new state and the list of changed keys. (e.g. `["focused"]`)
+ */
+export function onTargetChange(callback) {
+    window.addEventListener(
+        "freedomwall:targetchange",
+        event => callback(event.detail.target, event.detail.changed)
+    );
+};
+
+
+/**
+ * Get the settings of the wallpaper profile.
+ * Values keep the types written in `data.json` of the wallpaper profile.
+ * @returns {object} - Settings (`{"Key": Value}`)
+ */
+export function getSettings() {
+    return window.freedomWall.settings;
+};
+
+
+/**
+ * Register a callback to be called when the setting of the wallpaper profile or of an extension loaded into the page is changed.
+ * If the callback returns `true`, the page is not reloaded and the callback has to apply the new setting by itself.
+ * In the case of the wallpaper profile, `window.freedomWall.settings` is already updated when the callback is called.
+ * @param {function} callback - Callback to be passed the kind (`wallpaper` / `extension`), the name and the new setting
+ */
+export function onSettingChange(callback) {
+    window.addEventListener(
+        "freedomwall:settingchange",
+        event => {
+            if (callback(event.detail.kind, event.detail.name, event.detail.setting) === true)
+                event.preventDefault();
+        }
+    );
+};
+
+
+/**
+ * Check whether the wallpaper is paused.
+ * Wallpapers are paused while the window is not visible, or while a fullscreen app is in front if enabled in the settings.
+ * Videos and audios playing are paused and resumed automatically.
+ * @returns {boolean} - Whether it is paused
+ */
+export function isPaused() {
+    return window.freedomWall.paused;
+};
+
+
+/**
+ * Register a callback to be called when the wallpaper is paused or resumed.
+ * @param {function} callback - Callback to be passed whether it is paused
+ */
+export function onPauseChange(callback) {
+    window.addEventListener(
+        "freedomwall:pausechange",
+        event => callback(event.detail.paused)
+    );
+};
\ No newline at end of file
@@ -0,0 +1,72 @@
+//! FreedomWall.js - Trash
+
+import { request, SILENT, POST } from "./utils.js";
+
+
+/*
+ * Wallpaper profiles removed with `updateWallpaper(name, null, "remove")` are moved to the trash.
+ * The wallpaper settings dropped together are kept with it and come back when it is restored.
+ * An item in the trash is in the following format:
+ * ```js
+ * {
+ *     "id": "1700000000-Sample", // ID of the item
+ *     "name": "Sample", // Name of the wallpaper profile
+ *     "deletedAt": 1700000000, // UNIX time when it was removed
+ *     "targets": {"default": [...]} // Dropped wallpaper settings for each scene
+ * }
+ * ```
+ */
+
+
+/**
+ * Get the items in the trash from the newest one.
+ * @param {function} callback - Callback to be passed a list of items
+ */
+export function getTrash(callback) {
+    request(POST, "trash/items/get", "", callback, true);
+};
+
+
+/**
+ * Restore a wallpaper profile and its wallpaper settings from the trash.
+ * It fails if a wallpaper profile with the same name already exists.
+ * @param {string} id - ID of the item
+ */
+export function restoreTrash(id, reload=true) {
+    request(POST, `trash/items/update/${encodeURIComponent(id)}/restore`, "", SILENT, false, reload);
+};
+
+
+/**
+ * Remove an item from the trash permanently.
+ * @param {string} id - ID of the item
+ */
+export function removeTrash(id, reload=true) {
+    request(POST, `trash/items/update/${encodeURIComponent(id)}/remove`, "", SILENT, false, reload);
+};
+
+
+/**
+ * Remove all items from the trash permanently.
+ */
+export function emptyTrash(reload=true) {
+    request(POST, "trash/items/update", "", SILENT, false, reload);
+};
+
+
+/**
+ * Get how many days items are kept in the trash.
+ * @param {function} callback - Callback to be passed the number of days (`0` means unlimited)
+ */
+export function getTrashDays(callback) {
+    request(POST, "trash/days/get", "", callback, true);
+};
+
+
+/**
+ * Set how many days items are kept in the trash. Older items are removed immediately.
+ * @param {number} days - Number of days (`0` means unlimited)
+ */
+export function postTrashDays(days, reload=true) {
+    request(POST, "trash/days/update", days, SILENT, false, reload);
+};
\ No newline at end of file
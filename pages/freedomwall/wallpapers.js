@@ -10,8 +10,13 @@ import { request, SILENT, POST } from "./utils.js";
  * {
  *     "author": "Author",
  *     "description": "Description",
- *     "setting": {}, // Value passed as a query parameter to the wallpaper URL when loading the wallpaper.
- *     "force_size": true // Whether to force the width and height of the style of the element with the background class to the window size.
+ *     "setting": {}, // Settings passed to the wallpaper page as `window.freedomWall.settings`. Types of the values are kept.
+ *     "render": false, // Whether the HTML files of the profile are rendered with Tera before being shown. (`name`, `settings`, `locale`, `target` and `platform` can be used)
+ *     "layout": { // How to fit the elements with the background class to the window. `null` means leaving it to the page.
+ *         "mode": "cover", // `cover` / `contain` / `fill` / `center` / `tile` / `anchor`
+ *         "focus": {"x": 0.5, "y": 0.5} // Position to keep visible (`0.0` is left/top and `1.0` is right/bottom)
+ *     },
+ *     "extensions": null // Names of the extensions to be loaded into this wallpaper. `null` means all enabled extensions.
  * }
  * ```
  * @param {function} callback - Callback to be passed wallpapers.
@@ -21,6 +26,17 @@ export function getWallpapers(callback) {
 };
 
 
+/**
+ * Get a wallpaper profile.
+ * Wallpaper pages can only get their own profile.
+ * @param {string} name - Wallpaper profile name
+ * @param {function} callback - Callback to be passed wallpaper data.
+ */
+export function getWallpaper(name, callback) {
+    request(POST, `wallpapers/one/get/${name}`, "", callback, true);
+};
+
+
 /**
  * Register a new wallpaper profile from the template.
  * @param {string} template - Template name
@@ -0,0 +1,394 @@
+use std::{
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file, rename, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{
+    data_manager::{add_setting_path, check_setting_folder, extract_archive},
+    trash::TRASH,
+};
+
+/// スナップショットを置く設定フォルダの中のフォルダの名前です。
+const SNAPSHOTS: &str = "snapshots";
+/// 読み込む設定を展開し、置き換える前の設定を置いておく設定フォルダの中のフォルダの名前です。
+const IMPORTING: &str = ".importing";
+/// 設定のバックアップに含めないもの
+const EXCLUDED: [&str; 5] = [SNAPSHOTS, TRASH, IMPORTING, "instance.lock", ".staging"];
+
+/// スナップショットを残す数と期間の設定です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Retention {
+    /// 残す最大の数 (`0`で無制限)
+    pub count: usize,
+    /// 残す日数 (`0`で無制限)
+    pub days: u64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            count: 20,
+            days: 30,
+        }
+    }
+}
+
+/// スナップショットの情報です。
+/// スナップショットのファイル名は`<作った時のUNIX時間>-<理由>.zip`です。
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// ファイル名から拡張子を除いたもの
+    pub id: String,
+    /// 作った時のUNIX時間の秒数
+    pub created_at: u64,
+    /// 作った理由 (`manual`や`remove`等)
+    pub reason: String,
+    /// ファイルのバイト数
+    pub size: u64,
+}
+
+impl Snapshot {
+    /// スナップショットのファイルのパスから情報を作ります。形式が違う場合は`None`を返します。
+    fn from_path(path: &Path) -> Option<Self> {
+        if path.extension()? != "zip" {
+            return None;
+        };
+        let id = path.file_stem()?.to_str()?.to_string();
+        let (created_at, reason) = id.split_once('-')?;
+        Some(Self {
+            created_at: created_at.parse().ok()?,
+            reason: reason.to_string(),
+            size: path.metadata().ok()?.len(),
+            id: id,
+        })
+    }
+}
+
+/// 現在のUNIX時間の秒数を取得します。
+fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// スナップショットのフォルダのパスを取得します。
+fn get_folder() -> Result<PathBuf, String> {
+    Ok(PathBuf::from(add_setting_path(SNAPSHOTS)?))
+}
+
+/// スナップショットのIDとして使えるかどうかを調べます。
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        Err(t!("core.backup.notFound", id = id))
+    } else {
+        Ok(())
+    }
+}
+
+/// スナップショットに含める設定かどうかを調べます。
+/// 壁紙プロファイル等の画像や動画は含めず、設定ファイルと`data.json`と保存領域だけを含めます。
+fn is_configuration(name: &str) -> bool {
+    matches!(
+        name.split('/').collect::<Vec<&str>>().as_slice(),
+        ["data.json"]
+            | ["storage", ..]
+            | ["wallpapers" | "extensions"]
+            | ["wallpapers" | "extensions", _]
+            | ["wallpapers" | "extensions", _, "data.json"]
+    )
+}
+
+/// フォルダの中身の`filter`で選んだものをZIPファイルに書き込みます。
+fn write_folder<W: Write + io::Seek>(
+    zip: &mut ZipWriter<W>,
+    root: &Path,
+    folder: &Path,
+    filter: fn(&str) -> bool,
+) -> io::Result<()> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in read_dir(folder)? {
+        let path = entry?.path();
+        let name = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+        if (folder == root && EXCLUDED.contains(&name.as_str())) || !filter(&name) {
+            continue;
+        };
+        if path.is_dir() {
+            zip.add_directory(&name, options)?;
+            write_folder(zip, root, &path, filter)?;
+        } else {
+            zip.start_file(&name, options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        };
+    }
+    Ok(())
+}
+
+/// 設定フォルダの中身の`filter`で選んだものをまとめたZIPファイルを作ります。
+/// スナップショット等のバックアップに含めないものは除きます。
+fn write_archive(to: &Path, filter: fn(&str) -> bool) -> Result<(), String> {
+    let root = PathBuf::from(add_setting_path("")?);
+    let error = |e: String| {
+        format!(
+            "{}\nDetail: {}",
+            t!("core.general.failedWrite", path = &to.display().to_string()),
+            e
+        )
+    };
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent).map_err(|e| error(e.to_string()))?;
+    };
+    let mut zip = ZipWriter::new(File::create(to).map_err(|e| error(e.to_string()))?);
+    write_folder(&mut zip, &root, &root, filter).map_err(|e| error(e.to_string()))?;
+    zip.finish().map_err(|e| error(e.to_string()))?;
+    Ok(())
+}
+
+/// 設定フォルダの中身を壁紙プロファイル等のファイルも含めて全てまとめたZIPファイルを作ります。
+pub fn export(to: &Path) -> Result<(), String> {
+    write_archive(to, |_| true)
+}
+
+/// 読み込みで置き換えた設定です。
+/// 置き換える前の設定は残してあるので、設定を読み込み直せたら`commit`を、読み込み直せなかったら`rollback`を呼び出してください。
+pub struct Replaced {
+    /// 置き換えた設定フォルダの中のパス
+    paths: Vec<PathBuf>,
+}
+
+impl Replaced {
+    /// 置き換える前の設定を消し、残す数と期間を超えた古いスナップショットを消します。
+    pub fn commit(self, retention: &Retention) -> Result<(), String> {
+        let _ = remove_dir_all(get_importing()?);
+        prune(retention)
+    }
+
+    /// 置き換える前の設定に戻します。
+    pub fn rollback(self) -> Result<(), String> {
+        let root = get_root()?;
+        let old = get_importing()?.join("old");
+        for path in self.paths.iter().rev() {
+            let (target, before) = (root.join(path), old.join(path));
+            if target.is_dir() {
+                remove_dir_all(&target)
+            } else if target.exists() {
+                remove_file(&target)
+            } else {
+                Ok(())
+            }
+            .and_then(|_| {
+                if before.exists() {
+                    rename(&before, &target)
+                } else {
+                    Ok(())
+                }
+            })
+            .map_err(|e| format!("{}\nDetail: {}", t!("core.general.renameFailed"), e))?;
+        }
+        let _ = remove_dir_all(get_importing()?);
+        println!("Import rolled back.");
+        Ok(())
+    }
+}
+
+/// 設定フォルダのパスを取得します。
+fn get_root() -> Result<PathBuf, String> {
+    Ok(PathBuf::from(add_setting_path("")?))
+}
+
+/// 読み込む設定を置いておくフォルダのパスを取得します。
+fn get_importing() -> Result<PathBuf, String> {
+    Ok(PathBuf::from(add_setting_path(IMPORTING)?))
+}
+
+/// ZIPファイルを読み込み用のフォルダに展開し、設定として読み込めるかどうかを調べます。
+/// 読み込めない場合は展開したものを消します。
+fn stage(from: &Path, full: bool) -> Result<PathBuf, String> {
+    if !from.is_file() {
+        return Err(t!(
+            "core.general.failedRead",
+            path = &from.display().to_string()
+        ));
+    };
+    let importing = get_importing()?;
+    let _ = remove_dir_all(&importing);
+    let new = importing.join("new");
+    let result = create_dir_all(&new)
+        .map_err(|e| e.to_string())
+        .and_then(|_| extract_archive(from, &new))
+        .and_then(|_| check_setting_folder(&new, full))
+        // 元に戻せるように、置き換える前に今の設定のスナップショットを作っておく。
+        .and_then(|_| save("restore"));
+    if let Err(message) = result {
+        let _ = remove_dir_all(&importing);
+        return Err(message);
+    };
+    Ok(importing)
+}
+
+/// 設定フォルダの中の渡されたパスを、展開した設定のもので置き換えます。
+/// 途中で失敗した場合は置き換える前の設定に戻します。
+fn swap(importing: &Path, paths: Vec<PathBuf>) -> Result<Replaced, String> {
+    let root = get_root()?;
+    let mut replaced = Replaced { paths: Vec::new() };
+    for path in paths {
+        let (target, before, after) = (
+            root.join(&path),
+            importing.join("old").join(&path),
+            importing.join("new").join(&path),
+        );
+        let result = if target.exists() {
+            before
+                .parent()
+                .map_or(Ok(()), |x| create_dir_all(x))
+                .and_then(|_| rename(&target, &before))
+        } else {
+            Ok(())
+        }
+        .and_then(|_| {
+            // ここからは失敗しても元に戻せるように記録しておく。
+            replaced.paths.push(path);
+            if after.exists() {
+                rename(&after, &target)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            replaced.rollback()?;
+            return Err(format!(
+                "{}\nDetail: {}",
+                t!("core.general.renameFailed"),
+                e
+            ));
+        };
+    }
+    Ok(replaced)
+}
+
+/// `export`で作ったZIPファイルの中身で設定フォルダを置き換えます。
+/// 読み込めるかどうかを確認してから置き換え、置き換える前に今の設定のスナップショットを作ります。
+/// 設定を読み込み直すのは呼び出し側で行ってください。
+pub fn import(from: &Path) -> Result<Replaced, String> {
+    let importing = stage(from, true)?;
+    let mut paths = Vec::new();
+    for folder in [get_root()?, importing.join("new")] {
+        for entry in read_dir(&folder).map_err(|e| e.to_string())? {
+            let name = PathBuf::from(entry.map_err(|e| e.to_string())?.file_name());
+            if !EXCLUDED.iter().any(|x| name.as_path() == Path::new(x)) && !paths.contains(&name) {
+                paths.push(name);
+            };
+        }
+    }
+    swap(&importing, paths)
+}
+
+/// 今の設定のスナップショットのファイルを作り、そのパスを返します。
+fn save(reason: &str) -> Result<PathBuf, String> {
+    let folder = get_folder()?;
+    let mut now = get_now();
+    // 同じ秒に作られた場合に上書きしないようにする。
+    while folder.join(format!("{}-{}.zip", now, reason)).exists() {
+        now += 1;
+    }
+    let path = folder.join(format!("{}-{}.zip", now, reason));
+    write_archive(&path, is_configuration)?;
+    println!("Snapshot created: {}", path.display());
+    Ok(path)
+}
+
+/// 今の設定のスナップショットを作ります。
+/// `interval`秒以内に同じ理由で作ったスナップショットがある場合は、作らずにそれを返します。
+/// 作った後、残す数と期間を超えた古いスナップショットを消します。
+pub fn create(reason: &str, retention: &Retention, interval: u64) -> Result<Snapshot, String> {
+    let now = get_now();
+    if let Some(snapshot) = list()?.into_iter().find(|x| x.reason == reason) {
+        if now.saturating_sub(snapshot.created_at) < interval {
+            return Ok(snapshot);
+        };
+    };
+    let path = save(reason)?;
+    prune(retention)?;
+    Snapshot::from_path(&path).ok_or_else(|| t!("core.backup.notFound", id = reason))
+}
+
+/// スナップショットの一覧を新しいものから順番に取得します。
+pub fn list() -> Result<Vec<Snapshot>, String> {
+    let folder = get_folder()?;
+    if !folder.exists() {
+        return Ok(Vec::new());
+    };
+    let mut snapshots: Vec<Snapshot> = read_dir(&folder)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| Snapshot::from_path(&entry.ok()?.path()))
+        .collect();
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(snapshots)
+}
+
+/// スナップショットのファイルのパスを取得します。
+pub fn get_path(id: &str) -> Result<PathBuf, String> {
+    check_id(id)?;
+    let path = get_folder()?.join(format!("{}.zip", id));
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(t!("core.backup.notFound", id = id))
+    }
+}
+
+/// スナップショットの時点の設定に戻します。
+/// スナップショットには壁紙プロファイル等のファイルは含まれないので、設定ファイルと保存領域と、
+/// 今もある壁紙プロファイルと拡張機能の`data.json`だけを戻します。
+/// 設定を読み込み直すのは呼び出し側で行ってください。
+pub fn restore(id: &str) -> Result<Replaced, String> {
+    let importing = stage(&get_path(id)?, false)?;
+    let root = get_root()?;
+    let mut paths = vec![PathBuf::from("data.json"), PathBuf::from("storage")];
+    for kind in ["wallpapers", "extensions"] {
+        for entry in read_dir(importing.join("new").join(kind))
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let name = entry.file_name();
+            if root.join(kind).join(&name).is_dir() {
+                paths.push(Path::new(kind).join(&name).join("data.json"));
+            };
+        }
+    }
+    swap(&importing, paths)
+}
+
+/// スナップショットを消します。
+pub fn remove(id: &str) -> Result<(), String> {
+    remove_file(get_path(id)?).map_err(|e| e.to_string())
+}
+
+/// 残す数と期間を超えた古いスナップショットを消します。
+pub fn prune(retention: &Retention) -> Result<(), String> {
+    let now = get_now();
+    for (index, snapshot) in list()?.iter().enumerate() {
+        if (retention.count > 0 && index >= retention.count)
+            || (retention.days > 0
+                && now.saturating_sub(snapshot.created_at) > retention.days * 86400)
+        {
+            println!("Remove old snapshot: {}", snapshot.id);
+            remove(&snapshot.id)?;
+        };
+    }
+    Ok(())
+}
@@ -1,26 +1,39 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env::args,
     ffi::OsStr,
-    fs::{copy, create_dir, read_to_string, remove_dir_all, rename, File},
-    io::Write,
-    path::{Path, PathBuf},
+    fs::{copy, create_dir, create_dir_all, read_to_string, remove_dir_all, rename, File},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use semver::{Version, VersionReq};
 use smallvec::SmallVec;
+use zip::ZipArchive;
 
 use platform_dirs::AppDirs;
 use rust_i18n::t;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_str, to_string_pretty};
-
-use super::APPLICATION_NAME;
+use serde_json::{from_str, to_string_pretty, Value};
+
+use super::{
+    backup::Retention,
+    permission::{check_asset, Capability},
+    schedule::{Location, Now, Schedule},
+    storage,
+    trash::{self, TrashItem},
+    APPLICATION_NAME, VERSION,
+};
 
 #[cfg(target_os = "macos")]
 use super::platform::macos::get_bundle_path;
 
 const FAILED_JSON: &str = "JSON生成時にエラーが発生しました。";
+/// 拡張機能のインストール作業用のフォルダの名前です。
+const STAGING: &str = ".staging";
 const DATA_DEFAULT: &str = r#"{
     "language": "ja", "wallpapers": [], "update_interval": 0.05, "dev": false
 }"#;
@@ -57,8 +70,18 @@ fn get_application_name() -> String {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    /// テストで使う設定フォルダです。テストごとに別のフォルダを使います。
+    static TEST_SETTING_PATH: RefCell<Option<PathBuf>> = RefCell::new(None);
+}
+
 /// 渡されたパスに設定ファイルを保存する場所のパスを追加します。
 pub fn add_setting_path(path: &str) -> Result<String, String> {
+    #[cfg(test)]
+    if let Some(folder) = TEST_SETTING_PATH.with(|x| x.borrow().clone()) {
+        return Ok(format!("{}/{}", folder.display(), path));
+    };
     match AppDirs::new(Some(&get_application_name()), false) {
         Some(dir) => {
             let data_dir = dir.data_dir;
@@ -75,13 +98,70 @@ pub fn add_setting_path(path: &str) -> Result<String, String> {
     }
 }
 
+/// 壁紙の表示方法です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutMode {
+    /// 縦横比を保ったまま、ウィンドウ全体を覆うように拡大縮小します。
+    Cover,
+    /// 縦横比を保ったまま、全体がウィンドウに収まるように拡大縮小します。
+    Contain,
+    /// 縦横比を無視してウィンドウの大きさに合わせます。
+    Fill,
+    /// 元の大きさのまま中央に表示します。
+    Center,
+    /// 元の大きさのまま並べて表示します。
+    Tile,
+    /// 元の大きさのまま焦点の位置に合わせて表示します。
+    Anchor,
+}
+
+/// 壁紙の焦点の位置です。左上を`0.0`、右下を`1.0`とした割合です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Focus {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self { x: 0.5, y: 0.5 }
+    }
+}
+
+/// 壁紙の要素(`background`クラスの要素)をウィンドウにどう合わせるかの設定です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub mode: LayoutMode,
+    /// 拡大縮小や切り取りの際に優先して表示する位置
+    #[serde(default)]
+    pub focus: Focus,
+}
+
 /// 壁紙プロファイルの設定ファイルである`data.json`の構造体です。
 #[derive(Serialize, Deserialize, Clone)]
 pub struct WallpaperJson {
     pub author: String,
     pub description: String,
-    pub setting: HashMap<String, String>,
+    /// 壁紙のページに`window.freedomWall.settings`として渡す設定です。値の型はそのまま渡されます。
+    pub setting: HashMap<String, Value>,
+    /// 以前の形式の設定です。読み込み時に`layout`に移行します。
+    #[serde(default, skip_serializing)]
     pub force_size: bool,
+    /// 壁紙の表示方法です。`None`の場合は壁紙のページに任せます。
+    #[serde(default)]
+    pub layout: Option<Layout>,
+    /// `true`の場合、壁紙プロファイルのHTMLファイルをテンプレートとしてレンダリングしてから表示します。
+    #[serde(default)]
+    pub render: bool,
+    /// この壁紙で使う拡張機能の名前です。`None`の場合は有効な拡張機能を全て使います。
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+    /// 設定をURLのクエリパラメータにも入れるかどうかです。
+    /// 以前のバージョンのテンプレートから作った壁紙プロファイルは`PARAMS`から設定を読み込むので、書かれていない場合は`true`です。
+    /// `window.freedomWall.settings`を使う壁紙プロファイルでは`false`にします。
+    #[serde(default = "get_true")]
+    pub query_parameters: bool,
 }
 
 /// 壁紙の設定データの構造体です。
@@ -93,7 +173,7 @@ pub struct Wallpaper {
 }
 
 /// 壁紙ウィンドウのサイズ調整用のデータです。
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Shift {
     pub up: i32,
     pub down: i32,
@@ -101,14 +181,216 @@ pub struct Shift {
     pub right: i32,
 }
 
+/// プレイリストの壁紙プロファイルを切り替える順番です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistOrder {
+    Sequential,
+    Shuffle,
+}
+
+impl Default for PlaylistOrder {
+    fn default() -> Self {
+        Self::Sequential
+    }
+}
+
+/// 時間で壁紙プロファイルを切り替えるための設定です。
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Playlist {
+    /// 切り替えて使う壁紙プロファイルの名前
+    pub wallpapers: Vec<String>,
+    /// 切り替える間隔 (秒)
+    pub interval: f64,
+    #[serde(default)]
+    pub order: PlaylistOrder,
+    /// 切り替える時にフェードアウトとフェードインにかける時間 (秒)
+    #[serde(default)]
+    pub transition: f64,
+}
+
+/// プレイリストの現在の位置です。
+/// 再起動しても続きから切り替えられるように、壁紙設定のIDごとに`playlists.json`に保存されます。
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlaylistPosition {
+    pub index: usize,
+    /// 最後に切り替えた時刻 (UNIX時間の秒数)
+    pub changed_at: u64,
+}
+
+/// 最初のシーンの名前を取得します。シーンがなかった頃の設定はこのシーンになります。
+fn get_default_scene() -> String {
+    "default".to_string()
+}
+
+/// ゴミ箱に残す日数の既定値を取得します。
+fn get_trash_days() -> u64 {
+    30
+}
+
+/// `serde`の既定値を`true`にするための関数です。
+fn get_true() -> bool {
+    true
+}
+
 /// 背景対象となるウィンドウのデータの構造体です。
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Target {
+    /// 壁紙設定を見分けるためのIDです。並び替えや編集をしても変わりません。
+    #[serde(default)]
+    pub id: String,
+    /// この設定を使うかどうか。無効にしても設定は残ります。
+    #[serde(default = "get_true")]
+    pub enabled: bool,
     pub targets: SmallVec<[String; 4]>,
     pub exceptions: SmallVec<[String; 3]>,
     pub alpha: f64,
     pub wallpaper: String,
     pub shift: Shift,
+    /// この設定で使う拡張機能の名前です。`None`の場合は壁紙プロファイルの設定に従います。
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+    /// 壁紙プロファイルを時間で切り替える場合の設定です。ある場合は`wallpaper`の代わりに使います。
+    #[serde(default)]
+    pub playlist: Option<Playlist>,
+    /// 対象のウィンドウがフォーカスされていない時の透明度です。`None`の場合は`alpha`と同じです。
+    #[serde(default)]
+    pub unfocused_alpha: Option<f64>,
+    /// 対象のウィンドウがフォーカスされていない時に背景を隠すかどうか
+    #[serde(default)]
+    pub hide_unfocused: bool,
+    /// フォーカスが変わった時に透明度を変えるのにかける時間 (秒)
+    #[serde(default)]
+    pub fade: f64,
+    /// この設定を使う時間です。`None`の場合は常に使います。
+    /// 使わない時間は、その次に当てはまる設定が使われます。
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    /// 壁紙プロファイルの設定を上書きする値です。
+    /// 同じ壁紙プロファイルをアプリごとに違う設定で使うためのものです。
+    #[serde(default)]
+    pub setting: HashMap<String, Value>,
+}
+
+impl Target {
+    /// 対象のウィンドウのフォーカスに合わせた透明度を取得します。
+    /// `alpha`はフォーカスされている時の透明度です。
+    pub fn get_alpha(&self, focused: bool) -> f64 {
+        if focused {
+            self.alpha
+        } else if self.hide_unfocused {
+            0.0
+        } else {
+            self.unfocused_alpha.unwrap_or(self.alpha)
+        }
+    }
+
+    /// 壁紙プロファイルの設定に、この設定での上書きを反映したものを取得します。
+    pub fn merge_setting(&self, base: &HashMap<String, Value>) -> HashMap<String, Value> {
+        let mut setting = base.clone();
+        setting.extend(self.setting.iter().map(|(k, v)| (k.clone(), v.clone())));
+        setting
+    }
+
+    /// 今この設定を使う時間かどうかを調べます。
+    pub fn is_scheduled(&self, now: &Now) -> bool {
+        self.schedule.as_ref().map_or(true, |x| x.is_active(now))
+    }
+
+    /// 渡されたタイトルのウィンドウが背景の対象かどうかを調べます。
+    pub fn is_match(&self, title: &str) -> bool {
+        self.targets.iter().any(|target| title.contains(target))
+            && self
+                .exceptions
+                .iter()
+                .all(|exception| !title.contains(exception))
+    }
+
+    /// 使っている壁紙プロファイルの名前を変更します。プレイリストの中のものも変更します。
+    pub fn rename_wallpaper(&mut self, before: &str, after: &str) {
+        if self.wallpaper == before {
+            self.wallpaper = after.to_string();
+        };
+        if let Some(playlist) = &mut self.playlist {
+            for name in playlist.wallpapers.iter_mut().filter(|x| *x == before) {
+                *name = after.to_string();
+            }
+        };
+    }
+
+    /// 壁紙プロファイルを使わないようにします。
+    /// 使える壁紙プロファイルがなくなった場合は`false`を返します。
+    pub fn remove_wallpaper(&mut self, name: &str) -> bool {
+        if let Some(playlist) = &mut self.playlist {
+            playlist.wallpapers.retain(|x| x != name);
+            if self.wallpaper == name {
+                match playlist.wallpapers.first() {
+                    Some(first) => self.wallpaper = first.clone(),
+                    _ => return false,
+                };
+            };
+        };
+        self.wallpaper != name
+    }
+}
+
+/// 現在のUNIX時間の秒数を取得します。
+fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// 壁紙設定の新しいIDを作ります。
+fn new_target_id() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    format!(
+        "{:x}{:04x}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_nanos())
+            .unwrap_or(0),
+        COUNT.fetch_add(1, Ordering::Relaxed) & 0xffff
+    )
+}
+
+/// IDがないか他と重複している壁紙設定にIDを付けます。
+/// 付けた場合は`true`を返します。
+pub fn assign_target_ids(targets: &mut [Target]) -> bool {
+    let mut ids = HashSet::new();
+    let mut assigned = false;
+    for target in targets.iter_mut() {
+        if target.id.is_empty() || ids.contains(&target.id) {
+            target.id = new_target_id();
+            assigned = true;
+        };
+        ids.insert(target.id.clone());
+    }
+    assigned
+}
+
+/// 全ての壁紙設定に新しいIDを付けます。
+/// 壁紙設定を複製した場合に使います。
+pub fn renew_target_ids(targets: &mut [Target]) {
+    for target in targets.iter_mut() {
+        target.id = new_target_id();
+    }
+}
+
+/// 渡された名前の壁紙プロファイルを壁紙設定から外します。
+/// 使える壁紙プロファイルがなくなって消した壁紙設定を、消す前の状態で返します。
+pub fn remove_wallpaper_from(targets: &mut SmallVec<[Target; 5]>, name: &str) -> Vec<Target> {
+    let mut removed = Vec::new();
+    targets.retain(|target| {
+        let before = target.clone();
+        let keep = target.remove_wallpaper(name);
+        if !keep {
+            removed.push(before);
+        };
+        keep
+    });
+    removed
 }
 
 /// FreedomWallの設定ファイルの構造体です。
@@ -118,6 +400,49 @@ pub struct GeneralSetting {
     pub wallpapers: SmallVec<[Target; 5]>,
     pub update_interval: f32,
     pub dev: bool,
+    /// 壁紙と拡張機能からの読み込みを許可したファイルとフォルダ
+    #[serde(default)]
+    pub assets: Vec<String>,
+    /// 拡張機能ごとの有効/無効の設定 (ここにない拡張機能は有効)
+    #[serde(default)]
+    pub extensions: HashMap<String, bool>,
+    /// スケジュールの日の出と日の入りの計算に使う位置
+    #[serde(default)]
+    pub location: Option<Location>,
+    /// 全画面のアプリが一番前にある間、全ての壁紙を一時停止するかどうか
+    #[serde(default)]
+    pub pause_on_fullscreen: bool,
+    /// 全ての壁紙を止めているかどうか。止めている間は背景ウィンドウを作りません。
+    #[serde(default)]
+    pub paused: bool,
+    /// 使っているシーンの名前です。`wallpapers`がこのシーンの壁紙設定の一覧です。
+    #[serde(default = "get_default_scene")]
+    pub scene: String,
+    /// 使っていないシーンの壁紙設定の一覧
+    #[serde(default)]
+    pub scenes: HashMap<String, SmallVec<[Target; 5]>>,
+    /// シーンを切り替えるショートカットキー (シーンの名前と`Ctrl+Shift+1`のようなキー)
+    #[serde(default)]
+    pub scene_hotkeys: HashMap<String, String>,
+    /// 設定のスナップショットを残す数と期間
+    #[serde(default)]
+    pub snapshot_retention: Retention,
+    /// 削除した壁紙プロファイルをゴミ箱に残す日数 (`0`で無制限)
+    #[serde(default = "get_trash_days")]
+    pub trash_days: u64,
+    /// 既存の設定が使っているファイルの読み込みを許可したかどうか (一度だけ行う移行処理のため)
+    #[serde(default)]
+    pub assets_migrated: bool,
+}
+
+impl GeneralSetting {
+    /// 全てのシーンの名前を、使っているシーンも含めて名前順で取得します。
+    pub fn get_scene_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.scenes.keys().cloned().collect();
+        names.push(self.scene.clone());
+        names.sort();
+        names
+    }
 }
 
 /// 拡張機能のJSONデータの構造体です。
@@ -125,8 +450,36 @@ pub struct GeneralSetting {
 pub struct ExtensionJson {
     pub description: String,
     pub author: String,
+    /// 拡張機能のバージョン (セマンティックバージョニング)
     pub version: String,
     pub setting: HashMap<String, String>,
+    /// 壁紙のWebViewに追加で与える権限
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    /// 動作に必要なFreedomWallの最低バージョン
+    #[serde(default)]
+    pub min_app_version: Option<String>,
+    /// 依存する拡張機能の名前とバージョンの条件 (例: `{"Base": "^1.2"}`)
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+    /// 壁紙に読み込むスクリプトの拡張機能のフォルダからのパス
+    /// 空の場合は`init.js`を読み込みます。
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+    /// 読み込む順番 (小さい方が先) ですが、依存関係による順番の方が優先されます。
+    #[serde(default)]
+    pub load_order: i32,
+}
+
+impl ExtensionJson {
+    /// 壁紙に読み込むスクリプトのパスを取得します。
+    pub fn get_entry_points(&self) -> Vec<&str> {
+        if self.entry_points.is_empty() {
+            vec!["init.js"]
+        } else {
+            self.entry_points.iter().map(|x| x.as_str()).collect()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -144,7 +497,12 @@ pub struct DataManager {
     pub general: GeneralSetting,
     pub wallpapers: Wallpapers,
     pub templates: Templates,
+    /// 読み込む順番に並べられた拡張機能
     pub extensions: Extensions,
+    /// 読み込まなかった拡張機能の名前とその理由
+    pub rejected_extensions: HashMap<String, String>,
+    /// 壁紙設定のIDとそのプレイリストの現在の位置
+    pub playlists: HashMap<String, PlaylistPosition>,
 }
 
 fn failed_read(path: String) -> String {
@@ -273,6 +631,16 @@ fn write(path: &str, data: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// プレイリストの位置を読み込みます。
+/// ファイルがないか壊れている場合は最初からにします。
+fn read_playlists() -> HashMap<String, PlaylistPosition> {
+    add_setting_path("playlists.json")
+        .and_then(|path| read(&path))
+        .ok()
+        .and_then(|raw| from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
 /// 設定を読み込みます。
 fn read_setting() -> Result<GeneralSetting, String> {
     let path = add_setting_path("data.json")?;
@@ -287,7 +655,17 @@ fn read_setting() -> Result<GeneralSetting, String> {
     };
 
     match from_str::<GeneralSetting>(&raw) {
-        Ok(data) => Ok(data),
+        Ok(mut data) => {
+            // 以前のバージョンの設定にはIDがないので付けて保存しておく。
+            let mut assigned = assign_target_ids(&mut data.wallpapers);
+            for targets in data.scenes.values_mut() {
+                assigned |= assign_target_ids(targets);
+            }
+            if assigned {
+                write(&path, &to_string_pretty(&data).expect(FAILED_JSON))?;
+            };
+            Ok(data)
+        }
         Err(e) => Err(format!("{}\nCode: {}", failed_read(path), e)),
     }
 }
@@ -311,7 +689,21 @@ fn read_wallpapers() -> Result<Wallpapers, String> {
                         };
                     };
 
-                    if let Ok(data) = from_str::<WallpaperJson>(&raw) {
+                    if let Ok(mut data) = from_str::<WallpaperJson>(&raw) {
+                        // 後方互換 for 2.0.1: `force_size`を`layout`にする。
+                        if data.force_size {
+                            data.force_size = false;
+                            data.layout.get_or_insert(Layout {
+                                mode: LayoutMode::Fill,
+                                focus: Focus::default(),
+                            });
+                            if let Err(e) =
+                                write(file_path, &to_string_pretty(&data).expect(FAILED_JSON))
+                            {
+                                *error.borrow_mut() = e;
+                            };
+                        };
+
                         wallpapers.borrow_mut().push(Wallpaper {
                             name: get_name(dir).to_string(),
                             path: path,
@@ -358,14 +750,234 @@ fn read_templates() -> Result<Templates, String> {
     }
 }
 
+/// フォルダをその中身ごとコピーします。
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    create_dir_all(to)?;
+    for entry in from.read_dir()? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            copy(entry.path(), target)?;
+        };
+    }
+    Ok(())
+}
+
+/// ZIPファイルを展開します。
+/// 全てのファイルが一つのフォルダに入っている場合はそのフォルダの中身を展開して、そのフォルダの名前を返します。
+/// 展開先の外に出るようなパスのファイルは無視します。
+pub fn extract_archive(from: &Path, to: &Path) -> Result<Option<String>, String> {
+    let error = |e: String| format!("{}\nDetail: {}", failed_read(from.display().to_string()), e);
+    let mut archive = ZipArchive::new(File::open(from).map_err(|e| error(e.to_string()))?)
+        .map_err(|e| error(e.to_string()))?;
+
+    let names: Vec<PathBuf> = (0..archive.len())
+        .filter_map(|index| Some(archive.by_index(index).ok()?.enclosed_name()?.to_path_buf()))
+        .collect();
+    let prefix = match names.first().and_then(|x| x.components().next()) {
+        Some(first)
+            if !names.iter().any(|x| x == Path::new("data.json"))
+                && names.iter().all(|x| x.starts_with(first)) =>
+        {
+            PathBuf::from(first.as_os_str())
+        }
+        _ => PathBuf::new(),
+    };
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(|e| error(e.to_string()))?;
+        let name = match file.enclosed_name() {
+            Some(name) => name.to_path_buf(),
+            _ => continue,
+        };
+        let path = to.join(name.strip_prefix(&prefix).unwrap_or(&name));
+        if file.is_dir() {
+            create_dir_all(&path).map_err(|e| error(e.to_string()))?;
+        } else {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent).map_err(|e| error(e.to_string()))?;
+            };
+            io::copy(
+                &mut file,
+                &mut File::create(&path).map_err(|e| error(e.to_string()))?,
+            )
+            .map_err(|e| error(e.to_string()))?;
+        };
+    }
+
+    Ok(prefix
+        .to_str()
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string()))
+}
+
+/// 渡されたフォルダを設定フォルダとして読み込めるかどうかを調べます。
+/// 設定ファイルと全ての壁紙プロファイルと拡張機能の`data.json`を読み込み、`full`の場合は壁紙プロファイルの`index.html`も確認します。
+pub fn check_setting_folder(folder: &Path, full: bool) -> Result<(), String> {
+    let parse = |path: PathBuf, check: &dyn Fn(&str) -> Result<(), serde_json::Error>| {
+        let path = path.display().to_string();
+        // 読み込み時と同じ後方互換のための文字列交換をしてから確認する。
+        let raw = read(&path)?
+            .replace("\"updateInterval\"", "\"update_interval\"")
+            .replace("\"forceSize\"", "\"force_size\"");
+        check(&raw).map_err(|e| format!("{}\nCode: {}", failed_read(path), e))
+    };
+
+    parse(folder.join("data.json"), &|raw| {
+        from_str::<GeneralSetting>(raw).map(|_| ())
+    })?;
+    for dir in get_files(&folder.join("wallpapers"), true).unwrap_or_default() {
+        parse(dir.join("data.json"), &|raw| {
+            from_str::<WallpaperJson>(raw).map(|_| ())
+        })?;
+        if full && !dir.join("index.html").is_file() {
+            return Err(t!(
+                "core.general.wallpaperNotFound",
+                place = &dir.display().to_string(),
+                targets = "index.html"
+            ));
+        };
+    }
+    for dir in get_files(&folder.join("extensions"), true).unwrap_or_default() {
+        parse(dir.join("data.json"), &|raw| {
+            from_str::<ExtensionJson>(raw).map(|_| ())
+        })?;
+    }
+    Ok(())
+}
+
+/// 拡張機能の名前として使えるかどうかを調べます。
+fn is_valid_extension_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+/// 拡張機能の設定を調べます。
+/// 読み込めない拡張機能の場合はその理由を返します。
+fn check_extension(extension: &Extension) -> Result<(), String> {
+    let detail = &extension.detail;
+    if Version::parse(&detail.version).is_err() {
+        return Err(t!(
+            "core.extension.invalidVersion",
+            version = &detail.version
+        ));
+    };
+    if let Some(required) = &detail.min_app_version {
+        match Version::parse(required) {
+            Ok(version) => {
+                if Version::parse(VERSION).unwrap() < version {
+                    return Err(t!(
+                        "core.extension.incompatible",
+                        version = required,
+                        current = VERSION
+                    ));
+                };
+            }
+            _ => return Err(t!("core.extension.invalidVersion", version = required)),
+        };
+    };
+    for (name, requirement) in detail.dependencies.iter() {
+        if VersionReq::parse(requirement).is_err() {
+            return Err(t!(
+                "core.extension.invalidRequirement",
+                name = name,
+                requirement = requirement
+            ));
+        };
+    }
+    for entry in detail.get_entry_points() {
+        // 拡張機能のフォルダの外のファイルは読み込ませない。
+        let path = Path::new(entry);
+        if path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+            || !Path::new(&extension.path).join(path).is_file()
+        {
+            return Err(t!("core.extension.entryNotFound", path = entry));
+        };
+    }
+    Ok(())
+}
+
+/// 拡張機能を依存関係と`load_order`に従って読み込む順番に並べます。
+/// 依存先がない、またはバージョンが合わない拡張機能と、依存関係が循環している拡張機能は取り除き、その理由を`rejected`に入れます。
+fn sort_extensions(
+    mut extensions: Extensions,
+    rejected: &mut HashMap<String, String>,
+) -> Extensions {
+    // 依存先を使えない拡張機能を、なくなるまで取り除く。
+    while let Some((index, reason)) =
+        extensions
+            .iter()
+            .enumerate()
+            .find_map(|(index, extension)| {
+                extension
+                    .detail
+                    .dependencies
+                    .iter()
+                    .find_map(|(name, requirement)| {
+                        match extensions.iter().find(|x| &x.name == name) {
+                            Some(dependency) => {
+                                // バージョン等は`check_extension`で確認済み。
+                                let version = Version::parse(&dependency.detail.version).unwrap();
+                                if VersionReq::parse(requirement).unwrap().matches(&version) {
+                                    None
+                                } else {
+                                    Some(t!(
+                                        "core.extension.dependencyMismatch",
+                                        name = name,
+                                        requirement = requirement,
+                                        version = &dependency.detail.version
+                                    ))
+                                }
+                            }
+                            _ => Some(t!("core.extension.dependencyNotFound", name = name)),
+                        }
+                    })
+                    .map(|reason| (index, reason))
+            })
+    {
+        rejected.insert(extensions.remove(index).name, reason);
+    }
+
+    // 依存先が全て並べ終わっているものから順に並べる。
+    extensions.sort_by(|a, b| {
+        a.detail
+            .load_order
+            .cmp(&b.detail.load_order)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut sorted: Extensions = Vec::new();
+    while !extensions.is_empty() {
+        match extensions.iter().position(|extension| {
+            extension
+                .detail
+                .dependencies
+                .keys()
+                .all(|name| sorted.iter().any(|x| &x.name == name))
+        }) {
+            Some(index) => sorted.push(extensions.remove(index)),
+            _ => {
+                // 残りは依存関係が循環しているので読み込まない。
+                for extension in extensions.drain(..) {
+                    rejected.insert(extension.name, t!("core.extension.dependencyCycle"));
+                }
+            }
+        };
+    }
+    sorted
+}
+
 /// 拡張機能を読み込みます。
-fn read_extensions() -> Result<Extensions, String> {
+/// 読み込めない拡張機能は読み込まなかった理由と一緒に返します。
+fn read_extensions() -> Result<(Extensions, HashMap<String, String>), String> {
     let error = RefCell::new(String::new());
     let extensions = RefCell::new(Vec::new());
 
     search_files(
         "extensions",
-        ["init.js", "data.json"],
+        ["data.json"],
         |path, dir, file_name, file_path| {
             if file_name == "data.json" {
                 if let Ok(raw) = read(&file_path) {
@@ -388,7 +1000,21 @@ fn read_extensions() -> Result<Extensions, String> {
         return Err(error.into_inner());
     };
 
-    Ok(extensions.into_inner())
+    let mut rejected = HashMap::new();
+    let mut extensions = extensions.into_inner();
+    extensions.retain(|extension| match check_extension(extension) {
+        Ok(_) => true,
+        Err(reason) => {
+            rejected.insert(extension.name.clone(), reason);
+            false
+        }
+    });
+    let extensions = sort_extensions(extensions, &mut rejected);
+    for (name, reason) in rejected.iter() {
+        println!("Extension rejected: {}: {}", name, reason);
+    }
+
+    Ok((extensions, rejected))
 }
 
 /// DataManagerの実装です。
@@ -405,45 +1031,93 @@ impl DataManager {
 
         let path = &add_setting_path("")?;
 
-        if !Path::new(&path).exists() {
-            if create_dir(path).is_err() {
-                return Err(t!("core.general.createSettingFolderFailed", path = path));
-            };
+        if !Path::new(&path).exists() && create_dir(path).is_err() {
+            return Err(t!("core.general.createSettingFolderFailed", path = path));
+        };
 
-            // 初回起動時の場合は必要なファイルとフォルダ等を準備する。
-            for (file_name, default) in [
-                ("data.json", DATA_DEFAULT),
-                ("wallpapers", "_dir_"),
-                ("extensions", "_dir_"),
-            ] {
-                let mut error = String::new();
-                let path = add_setting_path(file_name)?;
-
-                if !Path::new(&path).exists() {
-                    // もし必要なファイルまたはフォルダがまだないのなら新しく作る。
-                    if default == "_dir_" {
-                        create_dir(&path).unwrap_or_else(|_| error = format!("Path:{}", path));
-                    } else {
-                        write(&path, default).unwrap_or_else(|_| {
-                            error = t!("core.general.failedWrite", path = file_name)
-                        });
-                    };
+        // 初回起動時の場合は必要なファイルとフォルダ等を準備する。
+        // 設定フォルダはロックファイルのために先に作られていることがあるので、中身は毎回確認する。
+        for (file_name, default) in [
+            ("data.json", DATA_DEFAULT),
+            ("wallpapers", "_dir_"),
+            ("extensions", "_dir_"),
+        ] {
+            let mut error = String::new();
+            let path = add_setting_path(file_name)?;
+
+            if !Path::new(&path).exists() {
+                // もし必要なファイルまたはフォルダがまだないのなら新しく作る。
+                if default == "_dir_" {
+                    create_dir(&path).unwrap_or_else(|_| error = format!("Path:{}", path));
+                } else {
+                    write(&path, default).unwrap_or_else(|_| {
+                        error = t!("core.general.failedWrite", path = file_name)
+                    });
                 };
+            };
 
-                if !error.is_empty() {
-                    return Err(error);
-                };
-            }
-        };
-        Ok(DataManager {
+            if !error.is_empty() {
+                return Err(error);
+            };
+        }
+        let (extensions, rejected_extensions) = read_extensions()?;
+        let mut data = DataManager {
             general: read_setting()?,
             wallpapers: read_wallpapers()?,
             templates: read_templates()?,
-            extensions: read_extensions()?,
-        })
+            extensions: extensions,
+            rejected_extensions: rejected_extensions,
+            playlists: read_playlists(),
+        };
+        data.migrate_assets()?;
+        Ok(data)
+    }
+
+    /// 以前のバージョンでは壁紙から設定フォルダの外のファイルも読み込めたので、
+    /// 既存の壁紙プロファイルと壁紙設定が使っているファイルの読み込みを一度だけ許可します。
+    fn migrate_assets(&mut self) -> Result<(), String> {
+        if self.general.assets_migrated {
+            return Ok(());
+        };
+        let setting_folder = PathBuf::from(add_setting_path("")?);
+        let mut approved: Vec<String> = Vec::new();
+        for setting in self.wallpapers.iter().map(|x| &x.detail.setting).chain(
+            self.general
+                .wallpapers
+                .iter()
+                .chain(self.general.scenes.values().flatten())
+                .map(|x| &x.setting),
+        ) {
+            for (key, value) in setting.iter() {
+                // 設定画面でファイルを選べる`...Path`という名前の設定だけを見る。
+                // `...FolderPath`という名前の設定はフォルダを選ぶものなので、フォルダも許可する。
+                let path = match value.as_str() {
+                    Some(path) if key.to_lowercase().ends_with("path") => path,
+                    _ => continue,
+                };
+                let file = Path::new(path);
+                if file.is_absolute()
+                    && (file.is_file()
+                        || key.to_lowercase().ends_with("folderpath") && file.is_dir())
+                    && !file.starts_with(&setting_folder)
+                    && !self
+                        .general
+                        .assets
+                        .iter()
+                        .chain(approved.iter())
+                        .any(|x| file.starts_with(x))
+                    && check_asset(path).is_ok()
+                {
+                    println!("Approve asset used by existing settings: {}", path);
+                    approved.push(path.to_string());
+                };
+            }
+        }
+        self.general.assets.extend(approved);
+        self.general.assets_migrated = true;
+        self.write_setting()
     }
 
-    /* 将来性を考慮して削除ではなくコメントアウト
     /// テンプレート情報を取得します。
     pub fn read_templates(&mut self) -> Result<&Templates, String> {
         self.templates = read_templates()?;
@@ -455,7 +1129,6 @@ impl DataManager {
         self.general = read_setting()?;
         Ok(&self.general)
     }
-    */
 
     /// 設定を書き込みます。
     pub fn write_setting(&self) -> Result<(), String> {
@@ -465,17 +1138,87 @@ impl DataManager {
         )
     }
 
-    /* 将来性を考慮して削除ではなくコメントアウト
+    /// プレイリストの位置を書き込みます。
+    /// 既に存在しない壁紙設定の位置は消します。
+    pub fn write_playlists(&mut self) -> Result<(), String> {
+        let (targets, scenes) = (&self.general.wallpapers, &self.general.scenes);
+        self.playlists.retain(|id, _| {
+            targets
+                .iter()
+                .chain(scenes.values().flatten())
+                .any(|target| &target.id == id)
+        });
+        write(
+            &add_setting_path("playlists.json")?,
+            &to_string_pretty(&self.playlists).expect(FAILED_JSON),
+        )
+    }
+
+    /// 壁紙設定で今使う壁紙プロファイルの名前を取得します。
+    /// プレイリストがある場合は、その現在の位置の壁紙プロファイルになります。
+    pub fn get_active_wallpaper<'a>(&self, target: &'a Target) -> &'a str {
+        match &target.playlist {
+            Some(playlist) if !playlist.wallpapers.is_empty() => {
+                let index = self.playlists.get(&target.id).map_or(0, |x| x.index);
+                &playlist.wallpapers[index % playlist.wallpapers.len()]
+            }
+            _ => &target.wallpaper,
+        }
+    }
+
+    /// 間隔が過ぎたプレイリストを次の壁紙プロファイルに進めます。
+    /// 位置が変わった場合は保存して`true`を返します。
+    pub fn rotate_playlists(&mut self) -> bool {
+        let now = get_now();
+        let mut changed = false;
+        for target in self.general.wallpapers.iter() {
+            let playlist = match &target.playlist {
+                Some(playlist) if !playlist.wallpapers.is_empty() => playlist,
+                _ => continue,
+            };
+            let length = playlist.wallpapers.len();
+            let position = self.playlists.entry(target.id.clone()).or_insert_with(|| {
+                changed = true;
+                PlaylistPosition {
+                    index: 0,
+                    changed_at: now,
+                }
+            });
+
+            if now.saturating_sub(position.changed_at) as f64 >= playlist.interval.max(1.0) {
+                position.index = match playlist.order {
+                    PlaylistOrder::Sequential => (position.index + 1) % length,
+                    // 同じ壁紙プロファイルが続かないように、今のもの以外から選ぶ。
+                    PlaylistOrder::Shuffle if length > 1 => {
+                        let random = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |x| x.subsec_nanos() as usize);
+                        (position.index + 1 + random % (length - 1)) % length
+                    }
+                    _ => 0,
+                };
+                position.changed_at = now;
+                changed = true;
+            };
+        }
+
+        if changed {
+            if let Err(message) = self.write_playlists() {
+                println!("Failed to save the playlist positions: {}", message);
+            };
+        };
+        changed
+    }
+
     /// 壁紙の設定を読み込みます。
     pub fn read_wallpapers(&mut self) -> Result<&Wallpapers, String> {
         self.wallpapers = read_wallpapers()?;
         Ok(&self.wallpapers)
     }
-    */
 
     /// 拡張機能を読み込みます。
     pub fn read_extensions(&mut self) -> Result<&Extensions, String> {
-        self.extensions = read_extensions()?;
+        (self.extensions, self.rejected_extensions) = read_extensions()?;
         Ok(&self.extensions)
     }
 
@@ -489,6 +1232,213 @@ impl DataManager {
         None
     }
 
+    /// 拡張機能をインストールします。
+    /// `source`には拡張機能のフォルダかその中の`data.json`、またはZIPファイルのパスを渡します。
+    /// `upgrade`に拡張機能の名前を渡した場合はその拡張機能を更新して、今の設定の値を引き継ぎます。
+    /// 拡張機能が新しく権限を要求している場合は、`approve`に名前と権限を渡して許可されたかどうかを確認します。
+    /// 途中で失敗した場合は元の状態に戻します。
+    pub fn install_extension(
+        &mut self,
+        source: &str,
+        upgrade: Option<&str>,
+        approve: &dyn Fn(&str, &[Capability]) -> bool,
+    ) -> Result<String, String> {
+        let mut source = PathBuf::from(source);
+        if source.file_name() == Some(OsStr::new("data.json")) {
+            source.pop();
+        };
+        let staging = PathBuf::from(add_setting_path(STAGING)?);
+        let _ = remove_dir_all(&staging);
+        let new = staging.join("new");
+
+        // 作業用のフォルダにコピーしてから確認する。
+        let result = if source.is_dir() {
+            copy_dir(&source, &new)
+                .map(|_| Some(get_name(&source).to_string()))
+                .map_err(|e| {
+                    format!(
+                        "{}\nDetail: {}",
+                        t!(
+                            "core.general.copyFailed",
+                            path = &source.display().to_string()
+                        ),
+                        e
+                    )
+                })
+        } else {
+            extract_archive(&source, &new).map(|name| {
+                name.or_else(|| {
+                    source
+                        .file_stem()
+                        .and_then(|x| x.to_str())
+                        .map(|x| x.to_string())
+                })
+            })
+        }
+        .and_then(|name| {
+            let name = upgrade.map(|x| x.to_string()).or(name).unwrap_or_default();
+            self.place_extension(&name, &new, &staging, upgrade.is_some(), approve)
+                .map(|_| name)
+        });
+        let _ = remove_dir_all(&staging);
+
+        let name = result?;
+        println!("Installed extension: {}", name);
+        self.read_extensions()?;
+        Ok(name)
+    }
+
+    /// 作業用のフォルダにある拡張機能を確認して、拡張機能のフォルダに移動します。
+    /// 更新の場合は古い方を作業用のフォルダに退避してから入れ替えて、失敗したら元に戻します。
+    fn place_extension(
+        &self,
+        name: &str,
+        new: &Path,
+        staging: &Path,
+        upgrade: bool,
+        approve: &dyn Fn(&str, &[Capability]) -> bool,
+    ) -> Result<(), String> {
+        if !is_valid_extension_name(name) {
+            return Err(t!("core.extension.invalidName", name = name));
+        };
+        let path = add_setting_path(&format!("extensions/{}", name))?;
+        let data_path = new.join("data.json").display().to_string();
+        let mut extension = Extension {
+            name: name.to_string(),
+            path: new.display().to_string(),
+            detail: match from_str::<ExtensionJson>(&read(&data_path)?) {
+                Ok(data) => data,
+                Err(e) => return Err(format!("{}\nCode: {}", failed_read(data_path), e)),
+            },
+        };
+        check_extension(&extension)?;
+
+        let exists = Path::new(&path).exists();
+        if !upgrade && exists {
+            return Err(t!("core.general.alreadyAdded", name = name));
+        };
+        if upgrade && !exists {
+            return Err(failed_read(path));
+        };
+        let old = if upgrade {
+            read(&format!("{}/data.json", path))
+                .ok()
+                .and_then(|raw| from_str::<ExtensionJson>(&raw).ok())
+        } else {
+            None
+        };
+
+        // 壁紙のWebViewに与えられる権限は、新しく要求されたものをユーザーに確認してから与える。
+        let added: Vec<Capability> = extension
+            .detail
+            .capabilities
+            .iter()
+            .filter(|x| {
+                old.as_ref()
+                    .map_or(true, |old| !old.capabilities.contains(x))
+            })
+            .copied()
+            .collect();
+        if !added.is_empty() && !approve(name, &added) {
+            return Err(t!("core.extension.capabilitiesDeclined", name = name));
+        };
+        if !upgrade {
+            return rename(new, &path).map_err(|_| t!("core.general.renameFailed"));
+        };
+
+        // 今の設定の値を引き継ぐ。新しいバージョンにない設定項目は引き継がない。
+        if let Some(old) = old {
+            for (key, value) in extension.detail.setting.iter_mut() {
+                if let Some(old_value) = old.setting.get(key) {
+                    *value = old_value.clone();
+                };
+            }
+            write(
+                &data_path,
+                &to_string_pretty(&extension.detail).expect(FAILED_JSON),
+            )?;
+        };
+
+        let backup = staging.join("old");
+        if rename(&path, &backup).is_err() {
+            return Err(t!("core.general.renameFailed"));
+        };
+        if rename(new, &path).is_err() {
+            let _ = rename(&backup, &path);
+            return Err(t!("core.general.renameFailed"));
+        };
+        Ok(())
+    }
+
+    /// 拡張機能をアンインストールします。
+    pub fn uninstall_extension(&mut self, name: &str) -> Result<(), String> {
+        let path = add_setting_path(&format!("extensions/{}", name))?;
+        if !is_valid_extension_name(name) || !Path::new(&path).exists() {
+            return Err(failed_read(path));
+        };
+        if remove_dir_all(&path).is_err() {
+            return Err(format!(
+                "{}\nDetail: {}",
+                t!("core.general.removeDirFailed"),
+                path
+            ));
+        };
+        println!("Uninstalled extension: {}", name);
+        storage::remove("extension", name)?;
+
+        self.general.extensions.remove(name);
+        self.write_setting()?;
+        self.read_extensions()?;
+        Ok(())
+    }
+
+    /// 拡張機能が有効かどうかを調べます。
+    pub fn is_extension_enabled(&self, name: &str) -> bool {
+        *self.general.extensions.get(name).unwrap_or(&true)
+    }
+
+    /// 背景ウィンドウに読み込む拡張機能を読み込む順番で取得します。
+    /// 壁紙設定に拡張機能の指定があればそれを、なければ壁紙プロファイルの指定を使います。
+    /// どちらにもない場合は全ての拡張機能を使います。指定された拡張機能の依存先も読み込みます。
+    /// 無効にされている拡張機能と、それに依存する拡張機能は含めません。
+    pub fn resolve_extensions(&self, target: &Target, wallpaper: &Wallpaper) -> Vec<Extension> {
+        let selected = target
+            .extensions
+            .as_ref()
+            .or(wallpaper.detail.extensions.as_ref());
+        let mut names: HashSet<&str> = self
+            .extensions
+            .iter()
+            .filter(|extension| {
+                selected
+                    .map(|names| names.contains(&extension.name))
+                    .unwrap_or(true)
+            })
+            .map(|extension| extension.name.as_str())
+            .collect();
+        // 依存先は必ず前にあるので、後ろから辿れば依存先の依存先まで含められる。
+        for extension in self.extensions.iter().rev() {
+            if names.contains(extension.name.as_str()) {
+                names.extend(extension.detail.dependencies.keys().map(|x| x.as_str()));
+            };
+        }
+
+        let mut result: Vec<Extension> = Vec::new();
+        for extension in self.extensions.iter() {
+            if names.contains(extension.name.as_str())
+                && self.is_extension_enabled(&extension.name)
+                && extension
+                    .detail
+                    .dependencies
+                    .keys()
+                    .all(|name| result.iter().any(|x| &x.name == name))
+            {
+                result.push(extension.clone());
+            };
+        }
+        result
+    }
+
     /// 拡張機能の設定を書き込みます。
     pub fn write_extension(&self, name: String) -> Result<(), String> {
         if let Some((_, extension)) = self.get_extension(&name) {
@@ -520,20 +1470,24 @@ impl DataManager {
         Ok(())
     }
 
-    /// 壁紙プロファイルを削除します。
-    pub fn remove_wallpaper(&mut self, index: usize) -> Result<(), String> {
-        let wallpaper = self.get_wallpaper_by_index(index)?;
-        match remove_dir_all(wallpaper.path.clone()) {
-            Ok(_) => {
-                self.wallpapers.remove(index);
-                Ok(())
-            }
-            _ => Err(format!(
-                "{}\nDetail: {}",
-                t!("core.general.removeDirFailed"),
-                wallpaper.path
-            )),
-        }
+    /// 壁紙プロファイルをゴミ箱に入れます。
+    /// 渡された壁紙設定は、戻す時のために一緒にゴミ箱へ入れます。
+    pub fn remove_wallpaper(
+        &mut self,
+        index: usize,
+        targets: HashMap<String, Vec<Target>>,
+    ) -> Result<(), String> {
+        trash::put(self.get_wallpaper_by_index(index)?, targets)?;
+        self.wallpapers.remove(index);
+        Ok(())
+    }
+
+    /// ゴミ箱から壁紙プロファイルを戻します。
+    /// 一緒に外した壁紙設定を返すので、それは呼び出し側で戻してください。
+    pub fn restore_wallpaper(&mut self, id: &str) -> Result<TrashItem, String> {
+        let item = trash::restore(id)?;
+        self.read_wallpapers()?;
+        Ok(item)
     }
 
     /// テンプレートから壁紙プロファイルを追加して書き込みをします。
@@ -610,7 +1564,7 @@ impl DataManager {
                     wallpaper.path = path;
                 };
             }
-            Ok(())
+            storage::mv("wallpaper", before, after)
         } else {
             Err(t!(
                 "core.general.findAppropriateWallpaperFailed",
@@ -618,15 +1572,67 @@ impl DataManager {
             ))
         }
     }
+}
 
-    /// 壁紙設定を取得ます。
-    pub fn get_wallpaper_setting(&self, name: &str) -> Vec<(usize, &Target)> {
-        let mut data = Vec::new();
-        for (index, target) in self.general.wallpapers.iter().enumerate() {
-            if target.wallpaper == name {
-                data.push((index, target));
-            };
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, process};
+
+    use super::*;
+
+    /// 空の設定フォルダを一時フォルダに作り、このスレッドでの設定フォルダにします。
+    fn use_temporary_setting_folder(name: &str) -> PathBuf {
+        let folder = temp_dir().join(format!("freedomwall-{}-{}", name, process::id()));
+        let _ = remove_dir_all(&folder);
+        create_dir_all(folder.join("wallpapers")).unwrap();
+        TEST_SETTING_PATH.with(|x| *x.borrow_mut() = Some(folder.clone()));
+        folder
+    }
+
+    #[test]
+    fn add_wallpaper_from_every_template() {
+        let folder = use_temporary_setting_folder("templates");
+        let mut data = DataManager {
+            general: from_str(DATA_DEFAULT).unwrap(),
+            wallpapers: Vec::new(),
+            templates: read_templates().unwrap(),
+            extensions: Vec::new(),
+            rejected_extensions: HashMap::new(),
+            playlists: HashMap::new(),
+        };
+        assert!(!data.templates.is_empty());
+
+        for template in data.templates.clone() {
+            data.add_wallpaper(template.clone(), template.clone())
+                .unwrap();
+            let path = folder.join("wallpapers").join(&template);
+
+            // 書き込まれた`data.json`が壁紙プロファイルとして読み込めること。
+            let detail =
+                from_str::<WallpaperJson>(&read_to_string(path.join("data.json")).unwrap())
+                    .unwrap();
+            assert!(!detail.setting.is_empty(), "{} has no settings", template);
+            assert!(
+                !detail.query_parameters,
+                "{} should use window.freedomWall.settings",
+                template
+            );
+
+            // ページが全ての設定を`window.freedomWall.settings`から読み込んでいること。
+            let html = read_to_string(path.join("index.html")).unwrap();
+            assert!(html.contains("<html>"), "{} has no entry point", template);
+            for key in detail.setting.keys() {
+                assert!(
+                    html.contains(&format!("settings.{}", key)),
+                    "{} does not use the setting {}",
+                    template,
+                    key
+                );
+            }
         }
-        data
+
+        // 作った壁紙プロファイルを全て読み込み直せること。
+        assert_eq!(read_wallpapers().unwrap().len(), data.templates.len());
+        let _ = remove_dir_all(folder);
     }
 }
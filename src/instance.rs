@@ -0,0 +1,287 @@
+use std::{
+    collections::hash_map::RandomState,
+    env::{args, current_exe},
+    fs::{create_dir_all, read_to_string, remove_file, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{BufRead, BufReader, Error, ErrorKind, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    process,
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string};
+use wry::application::event_loop::EventLoopProxy;
+
+use super::{
+    data_manager::add_setting_path, manager::UserEvents, platform::get_process_path,
+    APPLICATION_NAME,
+};
+
+const LOCK_FILE: &str = "instance.lock";
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 起動時に渡されたり、既に起動しているFreedomWallに送ったりするコマンドの列挙型です。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Command {
+    /// 設定画面を開きます。
+    Setting,
+    /// 設定ファイル等を読み込み直します。
+    Reload,
+    /// FreedomWallを終了します。
+    Quit,
+    /// 全ての壁紙を止めます。
+    Pause,
+    /// 止めた壁紙を再開します。
+    Resume,
+    /// 渡されたIDの壁紙設定を有効にします。
+    Enable(String),
+    /// 渡されたIDの壁紙設定を無効にします。
+    Disable(String),
+    /// 渡された名前のシーンに切り替えます。
+    Scene(String),
+}
+
+impl Command {
+    /// コマンドライン引数からコマンドを作ります。
+    /// 最初の引数が`test`の場合は開発用の設定フォルダを使うためのものなので無視します。
+    pub fn from_args() -> Result<Self, String> {
+        let mut data: Vec<String> = args()
+            .skip(1)
+            // 古いMacではアプリ起動時に`-psn_...`が渡されることがあるので無視する。
+            .filter(|x| !x.starts_with("-psn"))
+            .collect();
+        if data.first().map(|x| x == "test").unwrap_or(false) {
+            data.remove(0);
+        };
+
+        let argument = |command: &str| {
+            data.get(1)
+                .cloned()
+                .ok_or_else(|| t!("core.instance.missingArgument", command = command))
+        };
+        match data.first().map(|x| x.as_str()) {
+            None | Some("setting") => Ok(Self::Setting),
+            Some("reload") => Ok(Self::Reload),
+            Some("quit") => Ok(Self::Quit),
+            Some("pause") => Ok(Self::Pause),
+            Some("resume") => Ok(Self::Resume),
+            Some("enable") => Ok(Self::Enable(argument("enable")?)),
+            Some("disable") => Ok(Self::Disable(argument("disable")?)),
+            Some("scene") => Ok(Self::Scene(argument("scene")?)),
+            Some(command) => Err(t!("core.instance.unknownCommand", command = command)),
+        }
+    }
+}
+
+/// ロックファイルの中身の構造体です。
+/// `token`はコマンドを送るのに必要な文字列で、ロックファイルを読めるプロセスしか知りません。
+#[derive(Serialize, Deserialize)]
+struct Lock {
+    pid: u32,
+    port: u16,
+    #[serde(default)]
+    token: String,
+}
+
+/// 他のFreedomWallに送るコマンドとトークンの構造体です。
+#[derive(Serialize, Deserialize)]
+struct Message {
+    token: String,
+    command: Command,
+}
+
+/// 推測されにくいトークンを作ります。
+fn make_token() -> String {
+    (0..2)
+        .map(|_| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u32(process::id());
+            hasher.write_u128(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|x| x.as_nanos())
+                    .unwrap_or(0),
+            );
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// 渡されたPIDのプロセスが、このFreedomWallと同じ実行ファイルのものかどうかを調べます。
+/// クラッシュ後に別のプロセスが同じPIDを使っている場合にロックを消せるようにするためです。
+fn is_running_instance(pid: u32) -> bool {
+    if pid == process::id() {
+        return false;
+    };
+    match (get_process_path(pid), current_exe()) {
+        (Some(path), Ok(exe)) => path.file_name() == exe.file_name(),
+        // 実行ファイルが分からない場合は、動いているプロセスがあるので消さない。
+        (Some(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+/// 起動中のFreedomWallにコマンドを送ります。
+/// 返事がFreedomWallからのものでなければエラーとします。
+fn forward(lock: &Lock, command: &Command) -> Result<(), Error> {
+    let mut stream =
+        TcpStream::connect_timeout(&SocketAddr::from((Ipv4Addr::LOCALHOST, lock.port)), TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let message = Message {
+        token: lock.token.clone(),
+        command: command.clone(),
+    };
+    writeln!(stream, "{}", to_string(&message).unwrap())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == APPLICATION_NAME {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::InvalidData, reply))
+    }
+}
+
+/// ロックファイルを読み込みます。
+/// 書き込み途中の可能性があるので、読み込めなかった場合は少しだけ待ってからやり直します。
+fn read_lock(path: &str) -> Option<Lock> {
+    for _ in 0..3 {
+        if let Ok(raw) = read_to_string(path) {
+            if let Ok(lock) = from_str::<Lock>(&raw) {
+                return Some(lock);
+            };
+        };
+        thread::sleep(Duration::from_millis(100));
+    }
+    None
+}
+
+/// 他のFreedomWallから送られてくるコマンドを待ち受けるスレッドを動かします。
+/// 起動の途中でも受け付けて返事ができるように、受け取ったコマンドはイベントループに送れるようになるまで溜めておきます。
+/// トークンが違うものは他のプロセスからのものなので、返事をせずに無視します。
+fn accept(listener: TcpListener, token: String) -> Receiver<Command> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(TIMEOUT));
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err() {
+                continue;
+            };
+            match from_str::<Message>(line.trim()) {
+                Ok(message) if message.token == token => {
+                    if sender.send(message.command).is_err() {
+                        break;
+                    };
+                    let _ = writeln!(&stream, "{}", APPLICATION_NAME);
+                }
+                _ => println!("Ignored a command with a wrong token."),
+            };
+        }
+    });
+    receiver
+}
+
+/// 多重起動を防ぐためのロックを管理する構造体です。
+/// ロックファイルには起動中のプロセスのPIDとコマンドを待ち受けているポート番号、コマンドに必要なトークンが書き込まれます。
+pub struct Instance {
+    path: String,
+    /// 他のFreedomWallから受け取ってまだイベントループに送っていないコマンド
+    commands: Option<Receiver<Command>>,
+}
+
+impl Instance {
+    /// ロックを取得します。
+    /// 既に別のFreedomWallが起動している場合は渡されたコマンドをそちらに送り、`None`を返します。
+    /// ロックファイルがあっても応答がなく、書かれているPIDのプロセスがFreedomWallでない場合は、
+    /// クラッシュ等で残ったものとみなしてロックを取り直します。
+    pub fn acquire(command: &Command) -> Result<Option<Self>, String> {
+        let path = add_setting_path(LOCK_FILE)?;
+        let directory = add_setting_path("")?;
+        if create_dir_all(&directory).is_err() {
+            return Err(t!(
+                "core.general.createSettingFolderFailed",
+                path = &directory
+            ));
+        };
+
+        // ロックファイルを作ってから書き込むまでの間を短くするために、先に待ち受けるポートを用意しておく。
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .map_err(|e| format!("{}\nDetail: {}", t!("core.instance.listenFailed"), e))?;
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let lock = Lock {
+                        pid: process::id(),
+                        port: listener.local_addr().unwrap().port(),
+                        token: make_token(),
+                    };
+                    if file
+                        .write_all(to_string(&lock).unwrap().as_bytes())
+                        .is_err()
+                    {
+                        let _ = remove_file(&path);
+                        return Err(t!("core.general.failedWrite", path = &path));
+                    };
+                    // ロックを取れたらすぐにコマンドを受け付け始める。
+                    return Ok(Some(Self {
+                        path: path,
+                        commands: Some(accept(listener, lock.token)),
+                    }));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if let Some(lock) = read_lock(&path) {
+                        if forward(&lock, command).is_ok() {
+                            println!("Forwarded to the running instance (PID: {})", lock.pid);
+                            return Ok(None);
+                        };
+                        // 応答がなくてもFreedomWallが動いている場合は、固まっているだけかもしれないので消さない。
+                        if is_running_instance(lock.pid) {
+                            return Err(t!(
+                                "core.instance.notResponding",
+                                pid = &lock.pid.to_string(),
+                                path = &path
+                            ));
+                        };
+                    };
+                    // 応答がなくFreedomWallも動いていないので古いロックとして消す。
+                    println!("Remove stale lock: {}", path);
+                    let _ = remove_file(&path);
+                }
+                Err(_) => break,
+            };
+        }
+
+        Err(t!("core.instance.lockFailed", path = &path))
+    }
+
+    /// 他のFreedomWallから受け取ったコマンドをイベントループに送るスレッドを動かします。
+    /// それまでに受け取って溜めておいたコマンドも送られます。
+    pub fn listen(&mut self, proxy: EventLoopProxy<UserEvents>) -> Option<thread::JoinHandle<()>> {
+        let commands = self.commands.take()?;
+        Some(thread::spawn(move || {
+            for command in commands {
+                if proxy.send_event(UserEvents::Command(command)).is_err() {
+                    break;
+                };
+            }
+        }))
+    }
+
+    /// ロックを解放します。
+    pub fn release(&self) {
+        if remove_file(&self.path).is_ok() {
+            println!("Released lock: {}", self.path);
+        };
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
@@ -8,12 +8,21 @@ use wry::application::{
 
 use rust_i18n::i18n;
 
+mod backup;
 mod data_manager;
+mod instance;
 mod manager;
+mod permission;
 mod platform;
+mod render;
+mod schedule;
+mod serve;
+mod storage;
+mod trash;
 mod utils;
 mod window;
 
+use instance::{Command, Instance};
 use manager::{Manager, UserEvents};
 use utils::{error, escape_for_js};
 
@@ -23,13 +32,33 @@ pub const APPLICATION_NAME: &str = "FreedomWall";
 
 #[cfg(any(target_os = "macos", target_os = "windows"))]
 fn main() {
+    // 既に起動している場合はそちらにコマンドを渡して終了する。
+    let (command, instance) = match Command::from_args()
+        .and_then(|command| Ok((Instance::acquire(&command)?, command)))
+    {
+        Ok((Some(instance), command)) => (command, instance),
+        Ok((None, _)) => return,
+        Err(message) => {
+            error(&message);
+            return;
+        }
+    };
+    if let Command::Quit = command {
+        return;
+    };
+
     let event_loop: EventLoop<UserEvents> = EventLoop::with_user_event();
-    let manager_option = Manager::new(&event_loop, event_loop.create_proxy());
+    let manager_option = Manager::new(&event_loop, event_loop.create_proxy(), instance);
 
     if let Err(message) = manager_option {
         error(&message);
     } else {
         let mut manager = manager_option.unwrap();
+        // 起動していなかった場合も、一時停止等のコマンドは実行しておく。
+        match command {
+            Command::Setting | Command::Reload | Command::Quit => (),
+            command => manager.on_command(command),
+        };
 
         event_loop.run(move |event, event_loop_target, control_flow| {
             *control_flow = ControlFlow::Wait;
@@ -58,9 +87,23 @@ fn main() {
                         .unwrap();
                     manager.file_dialog = None;
                 }
+                Event::UserEvent(UserEvents::Command(Command::Quit)) => {
+                    // 他のFreedomWallから終了するように言われた場合。
+                    manager.stop();
+                    println!("Bye");
+                    *control_flow = ControlFlow::Exit;
+                }
+                Event::UserEvent(UserEvents::Command(command)) => {
+                    // 他のFreedomWallから渡されたコマンドを実行する。
+                    manager.on_command(command);
+                }
+                Event::GlobalShortcutEvent(id) => {
+                    // シーンを切り替えるショートカットキーが押された場合。
+                    manager.on_shortcut(id);
+                }
                 Event::UserEvent(UserEvents::Request(request)) => {
                     // APIリクエストを処理する。ここでやらなければエラーが起きてしまう。理由は`manager.rs`にて記述済み。
-                    manager.on_request(&request.uri, request.body.clone());
+                    manager.on_request(&request.uri, request.body.clone(), &request.permission);
                 }
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
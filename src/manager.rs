@@ -1,7 +1,11 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
-    fs::{canonicalize, read},
-    path::PathBuf,
+    fs::canonicalize,
+    mem::replace,
+    path::Path,
+    rc::Rc,
+    str::FromStr,
     sync::mpsc::{channel, Sender},
     thread,
     time::Duration,
@@ -10,13 +14,15 @@ use std::{
 use serde_json::{from_str, to_string};
 use smallvec::SmallVec;
 use url::Url;
-use urlencoding::decode;
+use urlencoding::{decode, encode};
 
-use rfd::FileDialog;
+use rfd::{FileDialog, MessageButtons, MessageLevel};
 use rust_i18n::{set_locale, t};
 use wry::{
     application::{
+        accelerator::{Accelerator, AcceleratorId},
         event_loop::{EventLoopProxy, EventLoopWindowTarget},
+        global_shortcut::ShortcutManager,
         window::WindowBuilder,
     },
     http::{Request, Response, ResponseBuilder},
@@ -25,13 +31,28 @@ use wry::{
 };
 
 use super::{
-    data_manager::{add_base, add_setting_path, DataManager, Target, Wallpaper, WallpaperJson},
-    platform::get_windows,
-    utils,
-    window::{Window, WindowTrait},
+    backup,
+    data_manager::{
+        add_setting_path, assign_target_ids, remove_wallpaper_from, renew_target_ids, DataManager,
+        Extension, Layout, LayoutMode, Target, Wallpaper, WallpaperJson,
+    },
+    instance::{Command, Instance},
+    permission::{check_asset, Capability, Permission},
+    platform::{get_monitor, get_windows, is_fullscreen, Rect, TargetState},
+    render::{RenderContext, SharedContext},
+    schedule::{Location, Now},
+    serve::{file2response, url2path},
+    storage, trash, utils,
+    window::{
+        fade_alpha, fade_out, get_wallpaper_url, set_paused, update_setting, update_state, Window,
+        WindowTrait,
+    },
     APPLICATION_NAME,
 };
 
+/// 自動で作るスナップショットを、同じ理由では作り直さない間隔の秒数です。
+const SNAPSHOT_INTERVAL: u64 = 600;
+
 /// ウィンドウ等を管理するための構造体です。
 pub struct Manager {
     pub windows: Vec<Window>,
@@ -43,6 +64,11 @@ pub struct Manager {
     pub heartbeat_sender: Sender<f32>,
     pub heartbeat: Option<thread::JoinHandle<()>>,
     pub count: usize,
+    pub instance: Instance,
+    pub listener: Option<thread::JoinHandle<()>>,
+    pub shortcuts: ShortcutManager,
+    /// 登録したショートカットキーと、それで切り替えるシーンの名前
+    pub hotkeys: Vec<(AcceleratorId, String)>,
 }
 
 /// レスポンスキューです。
@@ -55,6 +81,7 @@ pub struct CallbackResponse<'a> {
 pub struct RequestData {
     pub uri: String,
     pub body: String,
+    pub permission: Permission,
 }
 
 /// レスポンスを入れてmain.rsにて実行するのに使うユーザーイベントの列挙型です。
@@ -62,39 +89,40 @@ pub enum UserEvents {
     Request(RequestData),
     FileSelected(String),
     PassedInterval(),
+    Command(Command),
 }
 
 /// リクエストから適切なファイルを探し出しそれを返します。
-fn request2response(request: &Request) -> Result<Response, Error> {
-    #[cfg(target_os = "windows")]
-    let raw = request.uri().replace("wry://c//", "file:///c:/");
-    #[cfg(target_os = "windows")]
-    let uri = &raw;
-    #[cfg(target_os = "macos")]
+/// 送信元のWebViewに許可されていないフォルダのファイルの場合は403を返します。
+/// レンダリング用のデータが渡された場合、壁紙プロファイルのHTMLファイルはレンダリングしてから返します。
+fn request2response(
+    request: &Request,
+    permission: &Permission,
+    context: Option<&SharedContext>,
+) -> Result<Response, Error> {
     let uri = request.uri();
 
     if let Ok(url) = Url::parse(uri) {
-        if let Ok(path) = if uri.starts_with("wry://pages/") {
-            Ok(PathBuf::from(format!(
-                "{}/{}",
-                add_base("pages"),
-                url.path()
-            )))
-        } else {
-            url.to_file_path()
-        } {
+        if let Some(path) = url2path(&url) {
+            let path = canonicalize(path)?;
+            if !permission.can_read(&path) {
+                println!(
+                    "File request (Forbidden, window {}): {}",
+                    permission.window_id, uri
+                );
+                return ResponseBuilder::new()
+                    .status(403)
+                    .body(Vec::with_capacity(0));
+            };
+
             println!("File request: {}", uri);
-            let test;
-            return ResponseBuilder::new()
-                .mimetype(match mime_guess::from_path(&path).first() {
-                    Some(mime) => {
-                        test = format!("{}/{}", mime.type_(), mime.subtype());
-                        &test
-                    }
-                    _ => "text/plain",
-                })
-                .status(200)
-                .body(read(canonicalize(path.to_str().unwrap())?)?);
+            if let Some(context) = context {
+                let context = context.borrow();
+                if context.is_target(&path) {
+                    return context.render_response(&path);
+                };
+            };
+            return file2response(request, &path);
         };
     };
     println!("File request (NotFound): {}", uri);
@@ -104,22 +132,110 @@ fn request2response(request: &Request) -> Result<Response, Error> {
         .body(Vec::with_capacity(0))
 }
 
+/// 拡張機能が要求した権限をユーザーに見せて、許可するかどうかを確認します。
+fn approve_capabilities(name: &str, capabilities: &[Capability]) -> bool {
+    utils::confirm(&t!(
+        "core.confirm.capabilities",
+        name = name,
+        capabilities = &capabilities
+            .iter()
+            .map(|x| format!("- {}", x.describe()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// 壁紙設定のプレイリストの切り替えにかける時間を取得します。
+fn get_transition(target: &Target) -> f64 {
+    target.playlist.as_ref().map_or(0.0, |x| x.transition)
+}
+
+/// 壁紙の表示方法に合わせて`background`クラスの要素を調整するスクリプトを作ります。
+fn make_layout_script(layout: &Layout) -> String {
+    let (x, y) = match layout.mode {
+        LayoutMode::Center => (50.0, 50.0),
+        _ => (layout.focus.x * 100.0, layout.focus.y * 100.0),
+    };
+    let fit = match layout.mode {
+        LayoutMode::Cover => "cover",
+        LayoutMode::Contain => "contain",
+        LayoutMode::Fill => "fill",
+        _ => "none",
+    };
+    format!(
+        "// ウィンドウのサイズに壁紙のサイズを合わせるためのスクリプトを実行する。
+        let isTile = {};
+        let resizeElement = function (element) {{
+            element.style.width = `${{window.innerWidth}}px`;
+            element.style.height = `${{window.innerHeight}}px`;
+            // 並べて表示する場合は、要素の画像をページの背景にする。
+            let source = isTile && (element.currentSrc || element.src);
+            if (source) {{
+                document.body.style.backgroundImage = `url(\"${{source}}\")`;
+                element.style.visibility = 'hidden';
+            }};
+        }};
+        let resize = function () {{
+            for (let element of document.getElementsByClassName('background'))
+                resizeElement(element);
+            if (window.__backgrounds__)
+                for (let element of window.__backgrounds__)
+                    resizeElement(element);
+        }};
+        window.addEventListener('resize', resize);
+        // 後から読み込まれた画像にも反映させる。(`load`はバブリングしないためキャプチャで受け取る)
+        window.addEventListener('load', resize, true);
+        window.addEventListener('load', function (_) {{
+            // 画面全体にHTMLが表示されるようにする。
+            let style = document.createElement('style');
+            style.textContent = `
+                * {{
+                    padding: 0;
+                    margin: 0;
+                }}
+                body {{
+                    overflow: hidden;
+                    background-repeat: repeat;
+                    background-position: {x}% {y}%;
+                }}
+                .background, #background {{
+                    object-fit: {};
+                    object-position: {x}% {y}%;
+                }}
+            `;
+            document.head.appendChild(style);
+            resize();
+        }});",
+        layout.mode == LayoutMode::Tile,
+        fit,
+        x = x,
+        y = y
+    )
+}
+
 /// リクエストをイベントでラップしてイベントループに送信します。
 /// APIリクエストの処理を実行するのはmain.rsにあるイベントループのイベントハンドラー内からです。
 /// (ライフタイムがどうたらこうたらの関係上設計こうなっており、もし誰か対処法を知っているのなら教えてほしいです。)
-fn request2waiter(proxy: EventLoopProxy<UserEvents>, request: &Request) -> Result<Response, Error> {
+/// リクエストには送信元のWebViewの権限が付けられます。
+fn request2waiter(
+    proxy: EventLoopProxy<UserEvents>,
+    permission: &Permission,
+    context: Option<&SharedContext>,
+    request: &Request,
+) -> Result<Response, Error> {
     if request.uri().starts_with("wry://api/") {
         // APIリクエストのイベントを送信する。
         let _ = proxy.send_event(UserEvents::Request(RequestData {
             uri: request.uri().to_string(),
             body: String::from_utf8(request.body.clone()).unwrap_or_else(|_| "".to_string()),
+            permission: permission.clone(),
         }));
         ResponseBuilder::new()
             .header("Access-Control-Allow-Origin", "*")
             .status(201)
             .body(Vec::new())
     } else {
-        request2response(request)
+        request2response(request, permission, context)
     }
 }
 
@@ -128,6 +244,7 @@ impl Manager {
     pub fn new(
         event_loop: &EventLoopWindowTarget<UserEvents>,
         proxy: EventLoopProxy<UserEvents>,
+        instance: Instance,
     ) -> Result<Self, String> {
         // デフォルトの設定。
         set_locale("ja");
@@ -149,7 +266,13 @@ impl Manager {
             heartbeat_sender: tx,
             heartbeat: None,
             count: 0,
+            instance: instance,
+            listener: None,
+            shortcuts: ShortcutManager::new(event_loop),
+            hotkeys: Vec::new(),
         };
+        manager.register_hotkeys();
+        manager.purge_trash();
 
         // 設定画面のウィンドウを作る。
         manager.setting = Some(manager.make_setting_window(event_loop));
@@ -173,6 +296,8 @@ impl Manager {
                 break;
             };
         }));
+        // 他のFreedomWallから送られてくるコマンドを待ち受ける。
+        manager.listener = manager.instance.listen(manager.proxy.clone());
 
         Ok(manager)
     }
@@ -188,10 +313,15 @@ impl Manager {
             .build(event_loop)
             .expect("Failed to build the setting window.");
         let proxy = self.proxy.clone();
+        let permission = Permission::full(0, &self.data.general.assets);
         WebViewBuilder::new(window)
             .unwrap()
-            .with_custom_protocol("wry".into(), move |request| {
-                request2waiter(proxy.clone(), request)
+            .with_custom_protocol("wry".into(), {
+                let (proxy, permission) = (proxy.clone(), permission.clone());
+                move |request| request2waiter(proxy.clone(), &permission, None, request)
+            })
+            .with_custom_protocol("fw".into(), move |request| {
+                request2waiter(proxy.clone(), &permission, None, request)
             })
             .with_url("wry://pages/_home.html")
             .unwrap()
@@ -202,12 +332,17 @@ impl Manager {
     }
 
     /// 背景ウィンドウを追加します。
+    /// 渡された拡張機能のみが読み込まれます。
+    /// 背景対象のウィンドウの状態は`window.freedomWall.target`で壁紙のページから見られます。
     pub fn add(
         &mut self,
         event_loop: &EventLoopWindowTarget<UserEvents>,
         data: Wallpaper,
+        extensions: Vec<Extension>,
         alpha: f64,
-        target: String,
+        rule: String,
+        fade: f64,
+        state: TargetState,
     ) -> Result<(), String> {
         self.count += 1;
         let window = WindowBuilder::new()
@@ -218,16 +353,44 @@ impl Manager {
             .with_decorations(false)
             .build(event_loop)
             .expect("Failed to build the window.");
-        match &Url::parse_with_params(
-            &format!("wry://{}", format!("{}/index.html", &data.path)),
-            &data.detail.setting,
-        ) {
+        // 設定は初期化スクリプトで渡す。以前の形式の壁紙プロファイルのみURLにも入れる。
+        match &get_wallpaper_url(&data) {
             Ok(url) => {
                 let proxy = self.proxy.clone();
+                let permission = Permission::wallpaper(
+                    self.count,
+                    &data,
+                    &extensions,
+                    &self.data.general.assets,
+                );
+                // テンプレートエンジンを使う壁紙プロファイルの場合はレンダリング用のデータを用意する。
+                let context = if data.detail.render {
+                    add_setting_path(&format!("wallpapers/{}", data.name))
+                        .and_then(|path| canonicalize(path).map_err(|e| e.to_string()))
+                        .map(|root| {
+                            Rc::new(RefCell::new(RenderContext::new(
+                                data.name.clone(),
+                                data.detail.setting.clone(),
+                                self.data.general.language.clone(),
+                                state.clone(),
+                                root,
+                            )))
+                        })
+                        .ok()
+                } else {
+                    None
+                };
                 let webview = WebViewBuilder::new(window).unwrap()
-                    .with_custom_protocol(
-                        "wry".into(), move |request| request2waiter(proxy.clone(), request)
-                    )
+                    .with_custom_protocol("wry".into(), {
+                        let (proxy, permission) = (proxy.clone(), permission.clone());
+                        move |request| request2waiter(proxy.clone(), &permission, None, request)
+                    })
+                    .with_custom_protocol("fw".into(), {
+                        let context = context.clone();
+                        move |request| request2waiter(
+                            proxy.clone(), &permission, context.as_ref(), request
+                        )
+                    })
                     .with_url(&url.to_string()).unwrap()
                     .with_initialization_script(&format!(
                         "window.escapeHTML = function (str) {{
@@ -241,68 +404,130 @@ impl Manager {
                             // 拡張機能を読み込む。
                             let head = document.getElementsByTagName('head')[0];
                             console.log(head);
-                            for (let key of [{}]) {{
+                            for (let path of {}) {{
                                 var script = document.createElement('script');
     
                                 script.type = 'module';
-                                script.src = `wry://${{key}}/init.js`;
-                                console.log('Load extension:', key);
+                                // 依存関係の順番で実行されるようにする。
+                                script.async = false;
+                                script.src = `${{window.__WINDOWS__ ? 'https://fw.' : 'fw://'}}extension/${{path}}`;
+                                console.log('Load extension:', path);
     
                                 head.appendChild(script);
                             }};
                         }});
                         window.__WINDOWS__ = {};
-                        window.__WINDOW_ID__ = {};{}",
-                        if self.data.extensions.is_empty() { "".to_string() }
-                        else { format!(
-                            "\"{}\"",
-                            self.data.extensions.iter().map(|x| x.path.replace("\"", "\\\""))
-                                .collect::<Vec<String>>().join("\", \"")
-                        ) }, cfg!(target_os="windows").to_string(),
-                        self.count.to_string(), if data.detail.force_size {
-                            "// ウィンドウのサイズに壁紙のサイズを合わせるためのスクリプトを実行する。
-                            let resizeElement = function (element) {
-                                element.style.width = `${window.innerWidth}px`;
-                                element.style.height = `${window.innerHeight}px`;
-                            };
-                            let resize = function () {
-                                for (let element of document.getElementsByClassName('background')) {
-                                    console.log(element);
-                                    resizeElement(element);
-                                };
-                                if (window.__backgrounds__)
-                                    for (let element of window.__backgrounds__)
-                                        resizeElement(element);
-                            };
-                            window.addEventListener('resize', resize);
-                            window.addEventListener('load', function (_) {
-                                // 画面全体にHTMLが表示されるようにする。
-                                document.getElementsByTagName('head')[0].innerHTML += `
-                                    <style type=\"text/css\">
-                                    * {
-                                        padding: 0;
-                                        margin: 0;
-                                    }
-                                    body {
-                                        overflow: hidden;
-                                    }
-                                    #background {
-                                        object-fit: fill;
-                                    }
-                                    </style>
-                                `;
-                                resize();
-                            });"
-                        } else { "" }))
+                        window.__WINDOW_ID__ = {};
+
+                        // 背景対象のウィンドウの状態と壁紙プロファイルの設定
+                        window.freedomWall = window.freedomWall || {{}};
+                        window.freedomWall.target = {};
+                        window.freedomWall.settings = {};
+                        try {{
+                            // 再読み込みされた場合は最後に受け取った状態と設定を使う。
+                            let saved = sessionStorage.getItem('__freedomWallTarget__');
+                            if (saved) window.freedomWall.target = JSON.parse(saved);
+                            saved = sessionStorage.getItem('__freedomWallSettings__');
+                            if (saved) window.freedomWall.settings = JSON.parse(saved);
+                        }} catch (_) {{}};
+                        window.addEventListener('pagehide', function (_) {{
+                            try {{
+                                sessionStorage.setItem(
+                                    '__freedomWallTarget__', JSON.stringify(window.freedomWall.target)
+                                );
+                            }} catch (_) {{}};
+                        }});
+                        window.__updateTarget__ = function (target) {{
+                            let before = window.freedomWall.target;
+                            window.freedomWall.target = target;
+                            window.dispatchEvent(new CustomEvent('freedomwall:targetchange', {{
+                                detail: {{
+                                    target: target,
+                                    changed: Object.keys(target).filter(
+                                        key => JSON.stringify(target[key]) !== JSON.stringify(before[key])
+                                    )
+                                }}
+                            }}));
+                        }};
+
+                        // 壁紙プロファイルか拡張機能の設定が変わった際に呼ばれる。
+                        // ページ側で`preventDefault`されなかった場合は、このページのみを読み込み直して反映する。
+                        window.__updateSetting__ = function (kind, name, setting) {{
+                            if (kind === 'wallpaper') {{
+                                window.freedomWall.settings = setting;
+                                try {{
+                                    sessionStorage.setItem('__freedomWallSettings__', JSON.stringify(setting));
+                                }} catch (_) {{}};
+                            }};
+                            let event = new CustomEvent('freedomwall:settingchange', {{
+                                cancelable: true,
+                                detail: {{ kind: kind, name: name, setting: setting }}
+                            }});
+                            if (!window.dispatchEvent(event)) return;
+                            if (kind === 'wallpaper' && {}) {{
+                                // 以前の形式の壁紙プロファイルは設定をクエリパラメータから読み込むので、URLも変える。
+                                let url = new URL(location.href);
+                                url.search = new URLSearchParams(Object.entries(setting).map(
+                                    ([key, value]) => [key, typeof value === 'string' ? value : JSON.stringify(value)]
+                                )).toString();
+                                location.replace(url.toString());
+                            }} else location.reload();
+                        }};
+
+                        // 壁紙を一時停止または再開する。
+                        // 再生中だった動画と音声を止め、再開する時はそれらのみを再生し直す。
+                        window.freedomWall.paused = false;
+                        window.__setPaused__ = function (paused) {{
+                            window.freedomWall.paused = paused;
+                            for (let media of document.querySelectorAll('video, audio')) {{
+                                if (paused && !media.paused) {{
+                                    media.pause();
+                                    media.dataset.freedomWallPaused = 'true';
+                                }} else if (!paused && media.dataset.freedomWallPaused) {{
+                                    delete media.dataset.freedomWallPaused;
+                                    media.play();
+                                }};
+                            }};
+                            window.dispatchEvent(new CustomEvent('freedomwall:pausechange', {{
+                                detail: {{ paused: paused }}
+                            }}));
+                        }};{}",
+                        to_string(&extensions.iter().flat_map(|x| {
+                            x.detail.get_entry_points().into_iter().map(|entry| format!(
+                                "{}/{}", encode(&x.name),
+                                entry.split('/').map(|x| encode(x).to_string())
+                                    .collect::<Vec<String>>().join("/")
+                            )).collect::<Vec<String>>()
+                        }).collect::<Vec<String>>()).unwrap(), cfg!(target_os="windows").to_string(),
+                        self.count.to_string(), to_string(&state).unwrap(),
+                        to_string(&data.detail.setting).unwrap(),
+                        data.detail.query_parameters.to_string(),
+                        data.detail.layout.as_ref().map(make_layout_script).unwrap_or_default()))
+                    .with_initialization_script(&if fade > 0.0 {
+                        // プレイリストの切り替え時にフェードインさせる。
+                        format!(
+                            "document.addEventListener('DOMContentLoaded', function (_) {{
+                                document.documentElement.animate(
+                                    [{{ opacity: 0 }}, {{ opacity: 1 }}], {{ duration: {} }}
+                                );
+                            }});",
+                            fade * 1000.0
+                        )
+                    } else { String::new() })
                     .with_devtools(self.data.general.dev)
                     .build().expect("Failed to build the webview.");
 
-                let mut new = Window::new(data, webview, self.count, target);
+                let mut new = Window::new(data, webview, self.count, state.title.clone());
+                new.state = Some(state);
+                new.extensions = extensions.iter().map(|x| x.name.clone()).collect();
+                new.rule = rule;
+                new.render = context;
                 // 下準備をする。
                 if !self.data.general.dev {
                     new.set_click_through(true);
                 };
                 new.set_transparent(alpha);
+                new.alpha = alpha;
                 // 開発者モードが有効なら開発者ツールを表示する。
                 if self.data.general.dev {
                     new.webview.open_devtools();
@@ -321,12 +546,39 @@ impl Manager {
         &mut self,
         event_loop: &EventLoopWindowTarget<UserEvents>,
     ) -> Result<(), String> {
-        let (titles, rects) = get_windows();
+        // 全ての壁紙を止めている間は何もしない。背景ウィンドウは止めた時に隠して一時停止してある。
+        if self.data.general.paused {
+            return Ok(());
+        };
+        let (titles, rects, processes, visibilities) = get_windows();
         let mut done = SmallVec::<[_; 5]>::new();
+        // 一番前に全画面のアプリがある場合、そのモニターにある他のウィンドウの背景は隠す。
+        let covering = titles
+            .iter()
+            .zip(&rects)
+            .zip(&visibilities)
+            .find(|((title, (_, main, _)), visibility)| {
+                *main && visibility.is_visible() && !title.contains("FreedomWall")
+            })
+            .map(|((_, (rect, _, _)), _)| Rect::from_rects(rect))
+            .filter(|area| is_fullscreen(event_loop, area))
+            .map(|area| get_monitor(event_loop, &area));
+        let paused = self.data.general.pause_on_fullscreen && covering.is_some();
+        // 間隔が過ぎたプレイリストを次の壁紙プロファイルに進める。
+        self.data.rotate_playlists();
+        // スケジュールの時間外の壁紙設定は使わない。時間外になった背景ウィンドウは最後に消される。
+        let now = Now::get(self.data.general.location);
         // DEBUG: println!("{}", self.windows.len());
 
         // 背景を設定すべきウィンドウを探す。
-        for (title, (rect, main, extra)) in titles.iter().zip(rects) {
+        // 取得したウィンドウは前にあるものから順番に並んでいる。
+        for (z_order, (((title, (rect, main, extra)), process), mut visibility)) in titles
+            .iter()
+            .zip(rects)
+            .zip(processes)
+            .zip(visibilities)
+            .enumerate()
+        {
             if title.contains("FreedomWall") {
                 continue;
             };
@@ -338,44 +590,76 @@ impl Manager {
             };
 
             let mut make = None;
-            for target in self.data.general.wallpapers.iter() {
+            for (index, target) in self.data.general.wallpapers.iter().enumerate() {
                 // 背景を設定すべきウィンドウかどうかを調べる。
-                if target.targets.iter().any(|target| title.contains(target))
-                    && (target.exceptions.len() == 0
-                        || target
-                            .exceptions
-                            .iter()
-                            .all(|exception| !title.contains(exception)))
-                {
-                    let mut first = true;
-                    for window in self.windows.iter_mut() {
-                        if window.wallpaper.name == target.wallpaper && &window.target == title {
-                            // もし対象のウィンドウなら背景ウィンドウのサイズの変更や移動をさせたりする。
-                            window.set_front(main);
-                            window.set_rect_from_vec(&target.shift, &rect);
-                            window.set_order(extra);
-                            done.push(window.webview.window().id());
-                            first = false;
-                            break;
-                        };
-                    }
-                    if first {
-                        make = Some((target.wallpaper.clone(), target.alpha, title));
+                if target.enabled && target.is_scheduled(&now) && target.is_match(title) {
+                    let area = Rect::from_rects(&rect);
+                    let monitor = get_monitor(event_loop, &area);
+                    // 最小化されたウィンドウ等の位置とサイズは正しくないので、見えている場合のみ調べる。
+                    visibility.fullscreen =
+                        visibility.is_visible() && is_fullscreen(event_loop, &area);
+                    let visible = visibility.is_visible()
+                        && (main || covering.as_ref().map_or(true, |x| x != &monitor));
+                    let state = TargetState {
+                        title: title.clone(),
+                        process: process,
+                        focused: main,
+                        rect: area,
+                        monitor: monitor,
+                        z_order: z_order,
+                        visibility: visibility,
+                    };
+                    match self
+                        .windows
+                        .iter_mut()
+                        .find(|window| window.rule == target.id && &window.target == title)
+                    {
+                        Some(window) => {
+                            update_state(window, state);
+                            if visible {
+                                // もし対象のウィンドウなら背景ウィンドウのサイズの変更や移動をさせたりする。
+                                window.set_front(main);
+                                window.set_rect_from_vec(&target.shift, &rect);
+                                window.set_order(extra);
+                                // フォーカスに合わせて透明度を変える。
+                                fade_alpha(window, target.get_alpha(main), target.fade);
+                            } else {
+                                // 見えていないウィンドウの背景は、消さずに隠して一時停止しておく。
+                                fade_alpha(window, 0.0, 0.0);
+                            };
+                            set_paused(window, paused || !visible);
+                            // プレイリストで壁紙プロファイルが切り替わった場合は、フェードアウトが終わってから作り直す。
+                            if window.wallpaper.name == self.data.get_active_wallpaper(target)
+                                || !fade_out(window, get_transition(target))
+                            {
+                                done.push(window.webview.window().id());
+                            };
+                        }
+                        // 見えていないウィンドウの背景は、見えるようになるまで作らない。
+                        _ if visible => make = Some((index, state)),
+                        _ => (),
                     };
                     break;
                 };
             }
 
-            if let Some(target) = make {
+            if let Some((index, state)) = make {
                 // もしまだ作っていない背景ウィンドウなら作る。
-                return if let Some(wallpaper) = self.data.get_wallpaper(&target.0) {
-                    println!("Add window: {}", target.2);
-                    let _ = self.add(event_loop, wallpaper.clone(), target.1, target.2.clone());
+                let target = &self.data.general.wallpapers[index];
+                let name = self.data.get_active_wallpaper(target);
+                return if let Some(mut wallpaper) = self.data.get_wallpaper(name) {
+                    println!("Add window: {}", state.title);
+                    // 壁紙設定ごとの上書きを反映した設定で作る。
+                    wallpaper.detail.setting = target.merge_setting(&wallpaper.detail.setting);
+                    let extensions = self.data.resolve_extensions(target, &wallpaper);
+                    let (alpha, rule) = (target.get_alpha(state.focused), target.id.clone());
+                    let fade = get_transition(target);
+                    let _ = self.add(event_loop, wallpaper, extensions, alpha, rule, fade, state);
                     Ok(())
                 } else {
                     Err(t!(
                         "core.general.findAppropriateWallpaperFailed",
-                        name = &target.0
+                        name = name
                     ))
                 };
             };
@@ -394,15 +678,45 @@ impl Manager {
     }
 
     /// APIリクエストを処理します。
-    pub fn on_request(&mut self, uri: &str, data: String) {
+    /// リクエストを送ってきたWebViewの権限にないものは拒否します。
+    pub fn on_request(&mut self, uri: &str, data: String, permission: &Permission) {
         // 色々整えたり下準備をする。
-        let tentative_path = decode(&uri.replace("wry://api/", "").replace("?", "/"))
-            .unwrap()
-            .to_string();
-        let mut path: Vec<&str> = tentative_path.split("/").collect();
+        let raw = uri.replace("wry://api/", "").replace("?", "/");
+        // 壁紙のページからも好きなURLを送れるので、形の正しくないリクエストで落ちないように400を返す。
+        // 返事に使うリクエストIDは数字のみなので、デコードする前に取り出しておく。
+        let request_id = raw
+            .split("/")
+            .nth(1)
+            .filter(|x| !x.is_empty() && x.chars().all(|x| x.is_ascii_digit()))
+            .map(|x| x.to_string());
+        // 保存領域のキー等に`/`を含められるように、区切ってから一つずつデコードする。
+        let decoded: Result<Vec<String>, _> = raw
+            .split("/")
+            .map(|x| decode(x).map(|x| x.to_string()))
+            .collect();
+        let segments = match (&request_id, decoded) {
+            (Some(_), Ok(segments)) if segments.len() >= 5 => segments,
+            _ => {
+                println!(
+                    "API request (BadRequest, window {}): {}",
+                    permission.window_id, uri
+                );
+                if let Some(request_id) = &request_id {
+                    self.reply(
+                        permission.window_id,
+                        request_id,
+                        CallbackResponse {
+                            status: "400",
+                            body: t!("core.general.badRequest"),
+                        },
+                    );
+                };
+                return;
+            }
+        };
+        let mut path: Vec<&str> = segments.iter().map(|x| x.as_str()).collect();
 
         let length = path.len();
-        assert!(length >= 5);
 
         let (ok, notfound) = (Ok("Ok".to_string()), Err("Not found".to_string()));
         let make_error = |body: String| CallbackResponse {
@@ -410,7 +724,9 @@ impl Manager {
             body: body,
         };
 
-        let window_id: usize = path.remove(0).parse().unwrap();
+        // URLにあるウィンドウIDはページ側で書き換えられるので使わない。
+        path.remove(0);
+        let window_id = permission.window_id;
         let request_id = path.remove(0);
 
         let mut write_mode = "";
@@ -418,6 +734,18 @@ impl Manager {
 
         println!("API request: {} {}", uri, data);
 
+        if !permission.allows(&path) {
+            println!("API request denied (window {}): {}", window_id, uri);
+            return self.reply(
+                window_id,
+                request_id,
+                CallbackResponse {
+                    status: "403",
+                    body: t!("core.permission.denied", endpoint = &path.join("/")),
+                },
+            );
+        };
+
         // リクエストを処理する。
         let tentative = match path[0] {
             "setting" => {
@@ -430,6 +758,11 @@ impl Manager {
                         if is_update {
                             if "jaen".contains(&data) {
                                 set_locale(&data);
+                                for window in self.windows.iter() {
+                                    if let Some(context) = &window.render {
+                                        context.borrow_mut().locale = data.clone();
+                                    };
+                                }
                                 self.data.general.language = data;
                                 ok
                             } else {
@@ -443,10 +776,11 @@ impl Manager {
                     "wallpapers" => {
                         if is_update {
                             if let Ok(wallpapers) = from_str::<Vec<Target>>(&data) {
-                                self.data.general.wallpapers = SmallVec::<_>::from(wallpapers);
-                                // 現在開かれている背景ウィンドウを消す。
-                                self.reset_windows();
-                                ok
+                                // 変更前の設定を残してから、現在開かれている背景ウィンドウに反映する。
+                                self.snapshot("wallpapers").map(|_| {
+                                    self.set_targets(SmallVec::<_>::from(wallpapers));
+                                    "Ok".to_string()
+                                })
                             } else {
                                 Err(t!("core.general.loadJsonFailed"))
                             }
@@ -454,6 +788,23 @@ impl Manager {
                             Ok(to_string(&self.data.general.wallpapers).unwrap())
                         }
                     }
+                    // 壁紙から読み込むことを許可したファイルとフォルダ
+                    "assets" => {
+                        if is_update {
+                            if let Ok(assets) = from_str::<Vec<String>>(&data) {
+                                assets.iter().try_for_each(|x| check_asset(x)).map(|_| {
+                                    self.data.general.assets = assets;
+                                    // 権限を更新するために背景ウィンドウを作り直す。
+                                    self.reset_windows();
+                                    "Ok".to_string()
+                                })
+                            } else {
+                                Err(t!("core.general.loadJsonFailed"))
+                            }
+                        } else {
+                            Ok(to_string(&self.data.general.assets).unwrap())
+                        }
+                    }
                     // 背景ウィンドウの位置とサイズ更新の設定
                     "interval" => {
                         if is_update {
@@ -478,6 +829,138 @@ impl Manager {
                             Ok((self.data.general.dev as usize).to_string())
                         }
                     }
+                    // 使っているシーンの名前 (更新で切り替え)
+                    "scene" => {
+                        if is_update {
+                            self.switch_scene(&data).map(|_| "Ok".to_string())
+                        } else {
+                            Ok(self.data.general.scene.clone())
+                        }
+                    }
+                    // シーンの一覧 (`setting/scenes/get`)
+                    // シーンの作成と更新 (`setting/scenes/update/<名前>`で、本文は壁紙設定の一覧か空で今のシーンの複製)
+                    // シーンの削除 (`setting/scenes/update/<名前>/remove`)
+                    "scenes" => {
+                        if !is_update {
+                            if path.len() >= 4 {
+                                if path[3] == self.data.general.scene {
+                                    Ok(to_string(&self.data.general.wallpapers).unwrap())
+                                } else {
+                                    self.data
+                                        .general
+                                        .scenes
+                                        .get(path[3])
+                                        .map(|x| to_string(x).unwrap())
+                                        .ok_or_else(|| {
+                                            t!("core.general.sceneNotFound", name = path[3])
+                                        })
+                                }
+                            } else {
+                                Ok(to_string(&self.data.general.get_scene_names()).unwrap())
+                            }
+                        } else if path.len() >= 5 && path[4] == "remove" {
+                            if path[3] == self.data.general.scene {
+                                Err(t!("core.general.removeActiveScene"))
+                            } else if let Err(message) = self.snapshot("scene") {
+                                Err(message)
+                            } else {
+                                self.data.general.scene_hotkeys.remove(path[3]);
+                                self.register_hotkeys();
+                                self.data
+                                    .general
+                                    .scenes
+                                    .remove(path[3])
+                                    .map(|_| "Ok".to_string())
+                                    .ok_or_else(|| t!("core.general.sceneNotFound", name = path[3]))
+                            }
+                        } else if path.len() >= 4 && !path[3].is_empty() {
+                            match if data.is_empty() {
+                                Ok(self.data.general.wallpapers.to_vec())
+                            } else {
+                                from_str::<Vec<Target>>(&data)
+                            } {
+                                Ok(targets) => {
+                                    let mut targets = SmallVec::<_>::from(targets);
+                                    if path[3] == self.data.general.scene {
+                                        self.set_targets(targets);
+                                    } else {
+                                        if data.is_empty() {
+                                            // 今のシーンの壁紙設定と同じIDにならないように、複製したものには新しいIDを付ける。
+                                            renew_target_ids(&mut targets);
+                                        } else {
+                                            assign_target_ids(&mut targets);
+                                        };
+                                        self.data
+                                            .general
+                                            .scenes
+                                            .insert(path[3].to_string(), targets);
+                                    };
+                                    ok
+                                }
+                                _ => Err(t!("core.general.loadJsonFailed")),
+                            }
+                        } else {
+                            notfound
+                        }
+                    }
+                    // シーンを切り替えるショートカットキー (`{"シーンの名前": "Ctrl+Shift+1"}`)
+                    "sceneHotkeys" => {
+                        if is_update {
+                            if let Ok(hotkeys) = from_str::<HashMap<String, String>>(&data) {
+                                self.data.general.scene_hotkeys = hotkeys;
+                                self.register_hotkeys();
+                                ok
+                            } else {
+                                Err(t!("core.general.loadJsonFailed"))
+                            }
+                        } else {
+                            Ok(to_string(&self.data.general.scene_hotkeys).unwrap())
+                        }
+                    }
+                    // 全ての壁紙を止めるかどうか。
+                    "paused" => {
+                        if is_update {
+                            self.set_global_pause(data == "1");
+                            ok
+                        } else {
+                            Ok((self.data.general.paused as usize).to_string())
+                        }
+                    }
+                    // 壁紙設定の有効/無効 (`setting/enabled/<get|update>/<ID>`)
+                    "enabled" if path.len() >= 4 => {
+                        if is_update {
+                            self.set_enabled(path[3], data == "1")
+                                .map(|_| "Ok".to_string())
+                        } else {
+                            self.find_target(path[3])
+                                .map(|target| (target.enabled as usize).to_string())
+                                .ok_or_else(|| t!("core.general.targetNotFound", id = path[3]))
+                        }
+                    }
+                    // 全画面のアプリが一番前にある間、全ての壁紙を一時停止するかどうか。
+                    "pauseOnFullscreen" => {
+                        if is_update {
+                            // 次の`process_windows`で反映される。
+                            self.data.general.pause_on_fullscreen = data == "1";
+                            ok
+                        } else {
+                            Ok((self.data.general.pause_on_fullscreen as usize).to_string())
+                        }
+                    }
+                    // スケジュールの日の出と日の入りの計算に使う位置 (`null`で未設定)
+                    "location" => {
+                        if is_update {
+                            if let Ok(location) = from_str::<Option<Location>>(&data) {
+                                // 次の`process_windows`で反映される。
+                                self.data.general.location = location;
+                                ok
+                            } else {
+                                Err(t!("core.general.loadJsonFailed"))
+                            }
+                        } else {
+                            Ok(to_string(&self.data.general.location).unwrap())
+                        }
+                    }
                     _ => notfound,
                 }
             }
@@ -503,14 +986,27 @@ impl Manager {
                         Ok(to_string(&response_data).unwrap())
                     }
                 }
-                // 壁紙プロファイルの削除か更新
+                // 壁紙プロファイルの取得、削除か更新
+                // wallpapers/one/get/<name>
                 // wallpapers/one/update/<name>/<subject>
                 "one" => {
-                    if is_update && length >= 5 {
+                    if !is_update && path.len() >= 4 {
+                        match self.data.get_wallpaper(path[3]) {
+                            Some(wallpaper) => Ok(to_string(&wallpaper.detail).unwrap()),
+                            _ => notfound,
+                        }
+                    } else if is_update && length >= 5 {
                         match self.data.get_wallpaper_index(path[3]) {
                             Some(index) => match if path[4] == "write" {
                                 match from_str::<WallpaperJson>(&data) {
-                                    Ok(value) => {
+                                    Ok(mut value) => {
+                                        // 壁紙は自分に読み込まれる拡張機能を変えられないようにする。
+                                        if permission.wallpaper.is_some() {
+                                            value.extensions = self.data.wallpapers[index]
+                                                .detail
+                                                .extensions
+                                                .clone();
+                                        };
                                         self.data.wallpapers[index].detail = value;
                                         self.data.write_wallpaper(index)
                                     }
@@ -521,23 +1017,13 @@ impl Manager {
                                     )),
                                 }
                             } else {
-                                self.data.remove_wallpaper(index)
+                                self.snapshot("remove")
+                                    .and_then(|_| self.trash_wallpaper(index))
                             } {
                                 Ok(_) => {
-                                    let mut update_queue = Vec::new();
-                                    for (index, _) in self.data.get_wallpaper_setting(path[3]) {
-                                        if path[4] == "remove" {
-                                            update_queue.push(index);
-                                        };
-                                    }
-
-                                    // 使われている壁紙設定を削除する。(削除時限定)
-                                    update_queue.sort();
-                                    update_queue.reverse();
-                                    for index in update_queue {
-                                        self.data.general.wallpapers.remove(index);
-                                    }
-                                    self.reset_windows();
+                                    if path[4] == "write" {
+                                        self.push_wallpaper(path[3]);
+                                    };
 
                                     // データを書き込む。
                                     match self.data.write_setting() {
@@ -559,19 +1045,12 @@ impl Manager {
                         if let Err(message) = self.data.mv_wallpaper(path[3], path[4]) {
                             Err(message)
                         } else {
-                            // 既に使われているプロファイルの場合は再設定を行う。
-                            let mut update_queue = Vec::new();
-                            for (index, _) in self.data.get_wallpaper_setting(path[3]) {
-                                update_queue.push(index);
+                            // 既に使われているプロファイルの場合は、並び順とIDを変えずに再設定を行う。
+                            let mut targets = self.data.general.wallpapers.clone();
+                            for target in targets.iter_mut() {
+                                target.rename_wallpaper(path[3], path[4]);
                             }
-                            update_queue.sort();
-                            update_queue.reverse();
-                            for index in update_queue {
-                                let mut before = self.data.general.wallpapers.remove(index);
-                                before.wallpaper = path[4].to_string();
-                                self.data.general.wallpapers.push(before);
-                            }
-                            self.reset_windows();
+                            self.set_targets(targets);
                             // 設定を書き込む。
                             match self.data.write_setting() {
                                 Ok(_) => ok,
@@ -587,6 +1066,93 @@ impl Manager {
             // templates/all/get
             // テンプレートの取得を行えます。
             "templates" => Ok(to_string(&self.data.templates).unwrap()),
+            // backup/...
+            // 設定のスナップショットとバックアップ
+            "backup" => match path[1] {
+                // backup/snapshots/get
+                // backup/snapshots/update (手動で作る)
+                // backup/snapshots/update/<ID>/<restore|remove>
+                "snapshots" => {
+                    let retention = self.data.general.snapshot_retention;
+                    if !is_update {
+                        backup::list().map(|x| to_string(&x).unwrap())
+                    } else if path.len() >= 5 {
+                        match path[4] {
+                            "restore" => backup::restore(path[3])
+                                .and_then(|replaced| self.apply_replaced(replaced))
+                                .map(|_| "Ok".to_string()),
+                            "remove" => backup::remove(path[3]).map(|_| "Ok".to_string()),
+                            _ => notfound,
+                        }
+                    } else {
+                        backup::create("manual", &retention, 0).map(|x| to_string(&x).unwrap())
+                    }
+                }
+                // スナップショットを残す数と期間
+                "retention" => {
+                    if is_update {
+                        match from_str::<backup::Retention>(&data) {
+                            Ok(retention) => {
+                                write_mode = "general";
+                                self.data.general.snapshot_retention = retention;
+                                backup::prune(&retention).map(|_| "Ok".to_string())
+                            }
+                            _ => Err(t!("core.general.loadJsonFailed")),
+                        }
+                    } else {
+                        Ok(to_string(&self.data.general.snapshot_retention).unwrap())
+                    }
+                }
+                // backup/archive/update/<export|import> (ZIPファイルのパス)
+                "archive" if is_update && path.len() >= 4 => match path[3] {
+                    "export" => backup::export(Path::new(&data)).map(|_| "Ok".to_string()),
+                    "import" => backup::import(Path::new(&data))
+                        .and_then(|replaced| self.apply_replaced(replaced))
+                        .map(|_| "Ok".to_string()),
+                    _ => notfound,
+                },
+                _ => notfound,
+            },
+            // trash/...
+            // ゴミ箱に入れた壁紙プロファイル
+            "trash" => match path[1] {
+                // trash/items/get
+                // trash/items/update (ゴミ箱を空にする)
+                // trash/items/update/<ID>/<restore|remove>
+                "items" => {
+                    if !is_update {
+                        trash::list().map(|x| to_string(&x).unwrap())
+                    } else if path.len() >= 5 {
+                        match path[4] {
+                            "restore" => self
+                                .restore_wallpaper(path[3])
+                                .and_then(|_| self.data.write_setting())
+                                .map(|_| "Ok".to_string()),
+                            "remove" => trash::remove(path[3]).map(|_| "Ok".to_string()),
+                            _ => notfound,
+                        }
+                    } else {
+                        trash::empty(0).map(|_| "Ok".to_string())
+                    }
+                }
+                // ゴミ箱に残す日数
+                "days" => {
+                    if is_update {
+                        match from_str::<u64>(&data) {
+                            Ok(days) => {
+                                write_mode = "general";
+                                self.data.general.trash_days = days;
+                                self.purge_trash();
+                                ok
+                            }
+                            _ => Err(t!("core.general.loadJsonFailed")),
+                        }
+                    } else {
+                        Ok(self.data.general.trash_days.to_string())
+                    }
+                }
+                _ => notfound,
+            },
             // extensions/
             // 拡張機能
             "extensions" => match path[1] {
@@ -598,9 +1164,47 @@ impl Manager {
                         }
                         Ok(to_string(&data).unwrap())
                     }
-                    "update" => notfound,
+                    // extensions/all/update (インストールするフォルダ、ZIPファイルのパス)
+                    "update" => {
+                        match self
+                            .data
+                            .install_extension(&data, None, &approve_capabilities)
+                        {
+                            Ok(name) => {
+                                self.refresh_windows();
+                                Ok(name)
+                            }
+                            Err(message) => Err(message),
+                        }
+                    }
                     _ => notfound,
                 },
+                // extensions/one/update/<name>/upgrade (新しいバージョンのフォルダ、ZIPファイルのパス)
+                // extensions/one/update/<name>/remove
+                // 読み込めなかった拡張機能も更新と削除はできるようにする。
+                "one" if is_update && path.len() >= 5 => {
+                    match match path[4] {
+                        "upgrade" => self.snapshot("extension").and_then(|_| {
+                            self.data
+                                .install_extension(&data, Some(path[3]), &approve_capabilities)
+                        }),
+                        "remove" => self
+                            .snapshot("extension")
+                            .and_then(|_| self.data.uninstall_extension(path[3]))
+                            .map(|_| "Ok".to_string()),
+                        _ => Err("Not found".to_string()),
+                    } {
+                        Ok(_) => {
+                            // 新しいバージョンのスクリプトを読み込ませるために、その拡張機能を使っている背景ウィンドウは作り直す。
+                            self.remove_windows(|window| {
+                                window.extensions.iter().any(|x| x == path[3])
+                            });
+                            self.refresh_windows();
+                            ok
+                        }
+                        Err(message) => Err(message),
+                    }
+                }
                 "one" => {
                     if length >= 4 {
                         match self.data.get_extension(path[3]) {
@@ -609,7 +1213,7 @@ impl Manager {
                                 "update" => match from_str::<HashMap<String, String>>(&data) {
                                     Ok(value) => {
                                         self.data.extensions[index].detail.setting = value;
-                                        self.reset_windows();
+                                        self.push_extension(path[3]);
                                         match self.data.write_extension(path[3].to_string()) {
                                             Ok(_) => ok,
                                             _ => {
@@ -627,21 +1231,110 @@ impl Manager {
                         notfound
                     }
                 }
+                // extensions/enabled/get
+                // extensions/enabled/update ({"拡張機能の名前": true, ...})
+                // 拡張機能の有効/無効の取得と更新
+                "enabled" => {
+                    if is_update {
+                        match from_str::<HashMap<String, bool>>(&data) {
+                            Ok(value) => {
+                                self.data.general.extensions.extend(value);
+                                // 読み込む拡張機能が変わった背景ウィンドウのみ作り直す。
+                                self.refresh_windows();
+                                match self.data.write_setting() {
+                                    Ok(_) => ok,
+                                    Err(message) => Err(message),
+                                }
+                            }
+                            _ => Err(t!("core.general.loadJsonFailed")),
+                        }
+                    } else {
+                        let mut data = HashMap::new();
+                        for extension in self.data.extensions.iter() {
+                            data.insert(
+                                &extension.name,
+                                self.data.is_extension_enabled(&extension.name),
+                            );
+                        }
+                        Ok(to_string(&data).unwrap())
+                    }
+                }
+                // extensions/rejected/get
+                // 読み込まなかった拡張機能とその理由
+                "rejected" => Ok(to_string(&self.data.rejected_extensions).unwrap()),
                 "reload" => match self.data.read_extensions() {
                     Ok(_) => ok,
                     Err(message) => Err(message),
                 },
                 _ => notfound,
             },
+            // storage/...
+            // storage/<wallpaper|extension>/get/<name>
+            // storage/<wallpaper|extension>/get/<name>/<key>
+            // storage/<wallpaper|extension>/update/<name>/<key> (値)
+            // storage/<wallpaper|extension>/update/<name>/<key>/delete
+            // 壁紙プロファイルと拡張機能ごとの保存領域 (キーは一つの区切りとしてパーセントエンコードする)
+            "storage" if path.len() >= 4 => match (path[2], path.get(4), path.get(5)) {
+                ("get", None, _) => {
+                    storage::load(path[1], path[3]).map(|store| to_string(&store).unwrap())
+                }
+                ("get", Some(key), _) => match storage::get(path[1], path[3], key) {
+                    Ok(Some(value)) => Ok(value),
+                    Ok(None) => notfound,
+                    Err(message) => Err(message),
+                },
+                ("update", Some(key), None) => {
+                    storage::set(path[1], path[3], key, data).map(|_| "Ok".to_string())
+                }
+                ("update", Some(key), Some(&"delete")) => {
+                    storage::delete(path[1], path[3], key).map(|_| "Ok".to_string())
+                }
+                _ => notfound,
+            },
             // gettext/<text>/get
             "gettext" => Ok(t!(path[2])),
+            // confirm/<種類>/... (本文はメッセージに入れるパス等)
+            // ネイティブのダイアログでユーザーに確認します。WebViewによっては`confirm`が使えないためです。
+            "confirm" => match match path[1] {
+                "approveAsset" => Some(t!("core.confirm.approveAsset", path = &data)),
+                "restoreSnapshot" => Some(t!("core.confirm.restoreSnapshot")),
+                "importArchive" => Some(t!("core.confirm.importArchive", path = &data)),
+                "removeTrash" => Some(t!("core.confirm.removeTrash")),
+                "emptyTrash" => Some(t!("core.confirm.emptyTrash")),
+                _ => None,
+            } {
+                Some(message) => Ok((utils::confirm(&message) as usize).to_string()),
+                _ => notfound,
+            },
+            // alert/<種類>/... (本文はメッセージに入れるパス等)
+            // ネイティブのダイアログでユーザーに知らせます。
+            "alert" => match match path[1] {
+                "exported" => Some(t!("core.alert.exported", path = &data)),
+                "loadJsonFailed" => Some(format!(
+                    "{}\nDetail: {}",
+                    t!("core.general.loadJsonFailed"),
+                    data
+                )),
+                _ => None,
+            } {
+                Some(message) => {
+                    utils::dialog(&message, MessageLevel::Info, MessageButtons::Ok);
+                    ok
+                }
+                _ => notfound,
+            },
             "open" => {
-                // open/.../...
-                // ファイル選択
+                // open/.../... (フォルダの場合は`open/folder/...`)
+                // ファイルかフォルダの選択
                 let cloned = self.proxy.clone();
+                let folder = path[1] == "folder";
                 self.file_dialog = Some(thread::spawn(move || {
                     let _ = cloned.send_event(UserEvents::FileSelected(
-                        match FileDialog::new().pick_file() {
+                        match if folder {
+                            FileDialog::new().pick_folder()
+                        } else {
+                            FileDialog::new().pick_file()
+                        } {
                             Some(path) => path.as_path().display().to_string(),
                             _ => {
                                 utils::error(&t!("core.general.failedRead"));
@@ -666,6 +1359,14 @@ impl Manager {
                 ok
             }
             "getPath" => add_setting_path(""),
+            // files/list/get (フォルダのパス)
+            // 読み込みが許可されたフォルダにあるファイルの一覧を取得します。
+            "files" => match path[1] {
+                "list" => permission
+                    .list_files(&data)
+                    .map(|files| to_string(&files).unwrap()),
+                _ => notfound,
+            },
             _ => notfound,
         };
 
@@ -688,7 +1389,11 @@ impl Manager {
             _ => make_error(tentative.err().unwrap()),
         };
 
-        // レスポンスをJavaScriptのコールバックで送る。
+        self.reply(window_id, request_id, response);
+    }
+
+    /// APIリクエストのレスポンスをJavaScriptのコールバックで送ります。
+    fn reply(&self, window_id: usize, request_id: &str, response: CallbackResponse) {
         let js = &format!(
             "window.__callbacks__['{}']({}, `{}`);",
             request_id,
@@ -708,6 +1413,237 @@ impl Manager {
         };
     }
 
+    /// 他のFreedomWallから渡されたコマンドを実行します。
+    /// 終了のコマンドはイベントループを止める必要があるのでmain.rsにて処理します。
+    pub fn on_command(&mut self, command: Command) {
+        println!("Command: {:?}", command);
+        match command {
+            Command::Setting => {
+                if let Some(webview) = &self.setting {
+                    let window = webview.window();
+                    window.set_minimized(false);
+                    window.set_visible(true);
+                    window.set_focus();
+                };
+            }
+            Command::Reload => {
+                if let Err(message) = self.reload() {
+                    utils::error(&message);
+                };
+            }
+            Command::Pause | Command::Resume => {
+                self.set_global_pause(matches!(command, Command::Pause));
+                self.save_command_result(Ok(()));
+            }
+            Command::Enable(id) => {
+                let result = self.set_enabled(&id, true);
+                self.save_command_result(result);
+            }
+            Command::Disable(id) => {
+                let result = self.set_enabled(&id, false);
+                self.save_command_result(result);
+            }
+            Command::Scene(name) => {
+                let result = self.switch_scene(&name);
+                self.save_command_result(result);
+            }
+            Command::Quit => (),
+        };
+    }
+
+    /// コマンドで変更した設定を保存して、設定画面に反映します。
+    fn save_command_result(&mut self, result: Result<(), String>) {
+        if let Err(message) = result.and_then(|_| self.data.write_setting()) {
+            utils::error(&message);
+        } else if let Some(webview) = &self.setting {
+            let _ = webview.evaluate_script("location.reload();");
+        };
+    }
+
+    /// 今の設定のスナップショットを作ります。消したり上書きしたりする操作の前に呼び出します。
+    /// 同じ理由のスナップショットは`SNAPSHOT_INTERVAL`秒に一つだけ作ります。
+    fn snapshot(&self, reason: &str) -> Result<(), String> {
+        backup::create(
+            reason,
+            &self.data.general.snapshot_retention,
+            SNAPSHOT_INTERVAL,
+        )
+        .map(|_| ())
+    }
+
+    /// 壁紙プロファイルをゴミ箱に入れ、それしか使っていなかった壁紙設定を全てのシーンから外します。
+    /// 外した壁紙設定はシーンごとにゴミ箱へ一緒に入れ、戻す時に使います。
+    fn trash_wallpaper(&mut self, index: usize) -> Result<(), String> {
+        let name = self.data.get_wallpaper_by_index(index)?.name.clone();
+        let mut targets = self.data.general.wallpapers.clone();
+        let mut scenes = self.data.general.scenes.clone();
+        let mut removed = HashMap::new();
+        removed.insert(
+            self.data.general.scene.clone(),
+            remove_wallpaper_from(&mut targets, &name),
+        );
+        for (scene, scene_targets) in scenes.iter_mut() {
+            removed.insert(scene.clone(), remove_wallpaper_from(scene_targets, &name));
+        }
+        removed.retain(|_, x| !x.is_empty());
+
+        self.data.remove_wallpaper(index, removed)?;
+        self.data.general.scenes = scenes;
+        self.set_targets(targets);
+        self.purge_trash();
+        Ok(())
+    }
+
+    /// ゴミ箱から壁紙プロファイルを戻し、一緒に外した壁紙設定を元のシーンに戻します。
+    /// 元のシーンがなくなっている場合は今のシーンに戻します。
+    fn restore_wallpaper(&mut self, id: &str) -> Result<(), String> {
+        let item = self.data.restore_wallpaper(id)?;
+        let mut targets = self.data.general.wallpapers.clone();
+        for (scene, removed) in item.targets {
+            match self.data.general.scenes.get_mut(&scene) {
+                Some(scene_targets) => {
+                    scene_targets.extend(removed);
+                    assign_target_ids(scene_targets);
+                }
+                _ => targets.extend(removed),
+            };
+        }
+        assign_target_ids(&mut targets);
+        self.set_targets(targets);
+        Ok(())
+    }
+
+    /// 残す日数を過ぎたものをゴミ箱から消します。失敗した場合はログに出して無視します。
+    fn purge_trash(&self) {
+        if self.data.general.trash_days > 0 {
+            if let Err(message) = trash::empty(self.data.general.trash_days) {
+                println!("Failed to empty the trash: {}", message);
+            };
+        };
+    }
+
+    /// 読み込みで置き換えた設定を読み込み直します。
+    /// 読み込み直せなかった場合は置き換える前の設定に戻して、エラーを返します。
+    fn apply_replaced(&mut self, replaced: backup::Replaced) -> Result<(), String> {
+        match self.reload() {
+            Ok(_) => replaced.commit(&self.data.general.snapshot_retention),
+            Err(message) => {
+                replaced.rollback()?;
+                self.reload()?;
+                Err(message)
+            }
+        }
+    }
+
+    /// シーンを切り替えるショートカットキーが押された時の処理をします。
+    pub fn on_shortcut(&mut self, id: AcceleratorId) {
+        if let Some((_, name)) = self.hotkeys.iter().find(|(x, _)| *x == id) {
+            let result = self.switch_scene(&name.clone());
+            self.save_command_result(result);
+        };
+    }
+
+    /// シーンを切り替えるショートカットキーを登録し直します。
+    /// 登録できなかったものはログに出して無視します。
+    pub fn register_hotkeys(&mut self) {
+        let _ = self.shortcuts.unregister_all();
+        self.hotkeys.clear();
+        for (name, hotkey) in self.data.general.scene_hotkeys.iter() {
+            match Accelerator::from_str(hotkey)
+                .map_err(|e| e.to_string())
+                .and_then(|x| self.shortcuts.register(x).map_err(|e| e.to_string()))
+            {
+                Ok(shortcut) => self.hotkeys.push((shortcut.id(), name.clone())),
+                Err(message) => println!("Failed to register hotkey ({}): {}", hotkey, message),
+            };
+        }
+    }
+
+    /// 渡された名前のシーンに切り替えます。
+    /// 新しいシーンでも同じウィンドウに同じ壁紙プロファイルを付ける背景ウィンドウは、作り直さずにそのまま使います。
+    pub fn switch_scene(&mut self, name: &str) -> Result<(), String> {
+        if self.data.general.scene == name {
+            return Ok(());
+        };
+        let targets = self
+            .data
+            .general
+            .scenes
+            .remove(name)
+            .ok_or_else(|| t!("core.general.sceneNotFound", name = name))?;
+        println!("Switch scene: {}", name);
+        let before = replace(&mut self.data.general.scene, name.to_string());
+        self.data
+            .general
+            .scenes
+            .insert(before, self.data.general.wallpapers.clone());
+
+        // 背景ウィンドウを新しいシーンの壁紙設定のものにする。
+        for window in self.windows.iter_mut() {
+            if let Some(target) = targets
+                .iter()
+                .find(|target| target.enabled && target.is_match(&window.target))
+            {
+                if self.data.get_active_wallpaper(target) == window.wallpaper.name {
+                    window.rule = target.id.clone();
+                };
+            };
+        }
+        self.set_targets(targets);
+        Ok(())
+    }
+
+    /// 全ての壁紙を止めるか再開します。
+    /// ページの状態が失われないように背景ウィンドウは閉じずに、隠して一時停止します。
+    /// 再開した場合は次の`process_windows`で表示と再生が元に戻ります。
+    pub fn set_global_pause(&mut self, paused: bool) {
+        self.data.general.paused = paused;
+        if paused {
+            for window in self.windows.iter_mut() {
+                fade_alpha(window, 0.0, 0.0);
+                set_paused(window, true);
+            }
+        };
+    }
+
+    /// 渡されたIDの壁紙設定の有効/無効を切り替えます。
+    /// 無効にした壁紙設定の背景ウィンドウはすぐに閉じ、有効にした場合は次の`process_windows`で作られます。
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Result<(), String> {
+        match self
+            .data
+            .general
+            .wallpapers
+            .iter_mut()
+            .find(|target| target.id == id)
+        {
+            Some(target) => {
+                target.enabled = enabled;
+                self.refresh_windows();
+                Ok(())
+            }
+            _ => Err(t!("core.general.targetNotFound", id = id)),
+        }
+    }
+
+    /// 設定ファイル等を読み込み直して、背景ウィンドウを作り直します。
+    pub fn reload(&mut self) -> Result<(), String> {
+        self.data.read_setting()?;
+        self.data.read_wallpapers()?;
+        self.data.read_templates()?;
+        self.data.read_extensions()?;
+
+        set_locale(&self.data.general.language);
+        let _ = self
+            .heartbeat_sender
+            .send(self.data.general.update_interval);
+        self.register_hotkeys();
+        self.reset_windows();
+        if let Some(webview) = &self.setting {
+            let _ = webview.evaluate_script("location.reload();");
+        };
+        Ok(())
+    }
+
     fn get_windows_range(&mut self) -> Vec<usize> {
         let mut range: Vec<usize> = (0..self.windows.len()).collect();
         range.reverse();
@@ -717,7 +1653,6 @@ impl Manager {
     /// ウィンドウを閉じます。
     pub fn remove(&mut self, index: usize) {
         println!("Remove window: {}", self.windows[index].target);
-        self.count -= 1;
         self.windows[index]
             .webview
             .evaluate_script("window.close();")
@@ -725,6 +1660,141 @@ impl Manager {
         self.windows.remove(index);
     }
 
+    /// 条件に合う背景ウィンドウを閉じます。
+    /// 閉じた背景ウィンドウは、まだ対象のウィンドウがあれば次の`process_windows`で作り直されます。
+    pub fn remove_windows<F: Fn(&Window) -> bool>(&mut self, predicate: F) {
+        for index in self.get_windows_range() {
+            if predicate(&self.windows[index]) {
+                self.remove(index);
+            };
+        }
+    }
+
+    /// 壁紙設定の一覧を置き換えて、動いている背景ウィンドウに反映します。
+    /// 新旧の一覧をIDで比べ、変わっていない壁紙設定の背景ウィンドウはそのままにします。
+    /// 変わった壁紙設定の背景ウィンドウは透明度をその場で変更し、位置の調整は次の`process_windows`で反映されます。
+    /// 削除された壁紙設定の背景ウィンドウは閉じ、追加された壁紙設定の背景ウィンドウは次の`process_windows`で作られます。
+    pub fn set_targets(&mut self, mut targets: SmallVec<[Target; 5]>) {
+        assign_target_ids(&mut targets);
+        let before = replace(&mut self.data.general.wallpapers, targets);
+
+        for index in self.get_windows_range() {
+            let window = &self.windows[index];
+            let after = self.find_target(&window.rule);
+            let before = before.iter().find(|target| target.id == window.rule);
+            match after {
+                Some(after) if Some(after) == before => (),
+                Some(after) if self.data.get_active_wallpaper(after) == window.wallpaper.name => {
+                    let alpha = after.get_alpha(window.state.as_ref().map_or(true, |x| x.focused));
+                    // 設定の上書きが変わった場合は新しい設定を送る。
+                    let setting = self
+                        .data
+                        .get_wallpaper(&window.wallpaper.name)
+                        .map(|wallpaper| after.merge_setting(&wallpaper.detail.setting))
+                        .filter(|setting| setting != &window.wallpaper.detail.setting);
+                    let window = &mut self.windows[index];
+                    window.fading = None;
+                    window.alpha = alpha;
+                    window.set_transparent(alpha);
+                    if let Some(setting) = setting {
+                        update_setting(window, setting);
+                    };
+                }
+                _ => self.remove(index),
+            };
+        }
+
+        // 対象でなくなったものや拡張機能が変わったものを作り直す。
+        self.refresh_windows();
+    }
+
+    /// IDから壁紙設定を探します。
+    fn find_target(&self, id: &str) -> Option<&Target> {
+        self.data
+            .general
+            .wallpapers
+            .iter()
+            .find(|target| target.id == id)
+    }
+
+    /// 壁紙プロファイル等の変更を動いている背景ウィンドウに反映します。
+    /// 使う壁紙プロファイルか読み込む拡張機能が変わった背景ウィンドウと、対象でなくなった背景ウィンドウのみ作り直します。
+    pub fn refresh_windows(&mut self) {
+        for index in self.get_windows_range() {
+            let window = &self.windows[index];
+            let keep = match self.find_target(&window.rule) {
+                Some(target)
+                    if target.enabled
+                        && self.data.get_active_wallpaper(target) == window.wallpaper.name
+                        && target.is_match(&window.target) =>
+                {
+                    match self.data.get_wallpaper(&window.wallpaper.name) {
+                        Some(wallpaper) => self
+                            .data
+                            .resolve_extensions(target, &wallpaper)
+                            .iter()
+                            .map(|x| &x.name)
+                            .eq(window.extensions.iter()),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+
+            if !keep {
+                self.remove(index);
+            };
+        }
+    }
+
+    /// 壁紙プロファイルの設定の変更を、その壁紙プロファイルを使っている背景ウィンドウに送ります。
+    /// `layout`のように背景ウィンドウを作る時に反映するものが変わった場合は作り直します。
+    pub fn push_wallpaper(&mut self, name: &str) {
+        let wallpaper = match self.data.get_wallpaper(name) {
+            Some(wallpaper) => wallpaper,
+            _ => return,
+        };
+        for index in self.get_windows_range() {
+            let window = &self.windows[index];
+            if window.wallpaper.name != name {
+                continue;
+            };
+            if window.wallpaper.detail.layout != wallpaper.detail.layout
+                || window.wallpaper.detail.render != wallpaper.detail.render
+            {
+                self.remove(index);
+                continue;
+            };
+            // 壁紙設定ごとの上書きを反映してから送る。
+            let setting = match self.find_target(&window.rule) {
+                Some(target) => target.merge_setting(&wallpaper.detail.setting),
+                _ => wallpaper.detail.setting.clone(),
+            };
+            let window = &mut self.windows[index];
+            window.wallpaper = wallpaper.clone();
+            update_setting(window, setting);
+        }
+
+        // 拡張機能の指定が変わった場合のため。
+        self.refresh_windows();
+    }
+
+    /// 拡張機能の設定の変更を、その拡張機能を読み込んでいる背景ウィンドウに送ります。
+    pub fn push_extension(&mut self, name: &str) {
+        if let Some((_, extension)) = self.data.get_extension(name) {
+            let script = format!(
+                "window.__updateSetting__('extension', {}, {});",
+                to_string(&extension.name).unwrap(),
+                to_string(&extension.detail.setting).unwrap()
+            );
+            for window in self.windows.iter() {
+                if window.extensions.iter().any(|x| x == name) {
+                    let _ = window.webview.evaluate_script(&script);
+                };
+            }
+        };
+    }
+
     /// 全ての背景ウィンドウをリセットします。
     pub fn reset_windows(&mut self) {
         println!("Reset windows");
@@ -739,7 +1809,7 @@ impl Manager {
             let _ = self.heartbeat_sender.send(0.0);
             handle.join().expect("Failed to join heartbeat thread.");
         };
-        self.data.general.wallpapers = SmallVec::<_>::new();
         self.reset_windows();
+        self.instance.release();
     }
 }
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashSet,
+    env::var_os,
+    fs::canonicalize,
+    path::{Path, PathBuf},
+};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    data_manager::{add_base, add_setting_path, Extension, Wallpaper},
+    serve::list_files,
+};
+
+/// APIを使うための権限の列挙型です。
+/// 拡張機能の`data.json`の`capabilities`にはこれをキャメルケースにしたものを書きます。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// 一般の設定の取得 (`setting/.../get`)
+    SettingRead,
+    /// 一般の設定の更新 (`setting/.../update`)
+    SettingWrite,
+    /// 壁紙プロファイルの取得 (`wallpapers/.../get`)
+    WallpapersRead,
+    /// 壁紙プロファイルの追加、更新、名前変更と削除
+    WallpapersWrite,
+    /// 拡張機能の取得 (`extensions/.../get`)
+    ExtensionsRead,
+    /// 拡張機能の設定の更新と再読み込み
+    ExtensionsWrite,
+    /// テンプレートの取得
+    TemplatesRead,
+    /// 自分自身の壁紙プロファイルと拡張機能の設定の取得と更新、それらの保存領域の使用
+    SelfSetting,
+    /// ファイル選択ダイアログ
+    Dialog,
+    /// フォルダを開く。
+    OpenFolder,
+    /// ウェブサイトを開く。
+    OpenWebsite,
+    /// 設定フォルダのパスの取得
+    GetPath,
+    /// 翻訳された文字列の取得
+    Gettext,
+}
+
+impl Capability {
+    /// ユーザーに見せるための説明を取得します。
+    pub fn describe(&self) -> String {
+        match self {
+            Self::SettingRead => t!("core.capability.settingRead"),
+            Self::SettingWrite => t!("core.capability.settingWrite"),
+            Self::WallpapersRead => t!("core.capability.wallpapersRead"),
+            Self::WallpapersWrite => t!("core.capability.wallpapersWrite"),
+            Self::ExtensionsRead => t!("core.capability.extensionsRead"),
+            Self::ExtensionsWrite => t!("core.capability.extensionsWrite"),
+            Self::TemplatesRead => t!("core.capability.templatesRead"),
+            Self::SelfSetting => t!("core.capability.selfSetting"),
+            Self::Dialog => t!("core.capability.dialog"),
+            Self::OpenFolder => t!("core.capability.openFolder"),
+            Self::OpenWebsite => t!("core.capability.openWebsite"),
+            Self::GetPath => t!("core.capability.getPath"),
+            Self::Gettext => t!("core.capability.gettext"),
+        }
+    }
+}
+
+pub type Capabilities = HashSet<Capability>;
+
+/// 全ての権限です。設定画面はこれを持ちます。
+const ALL: [Capability; 13] = [
+    Capability::SettingRead,
+    Capability::SettingWrite,
+    Capability::WallpapersRead,
+    Capability::WallpapersWrite,
+    Capability::ExtensionsRead,
+    Capability::ExtensionsWrite,
+    Capability::TemplatesRead,
+    Capability::SelfSetting,
+    Capability::Dialog,
+    Capability::OpenFolder,
+    Capability::OpenWebsite,
+    Capability::GetPath,
+    Capability::Gettext,
+];
+/// 壁紙のWebViewが最初から持つ権限です。
+const WALLPAPER: [Capability; 2] = [Capability::Gettext, Capability::SelfSetting];
+
+/// APIのエンドポイントを使うのに必要な権限を取得します。
+/// 渡すパスはウィンドウIDとリクエストIDを取り除いたものです。
+/// 知らないエンドポイントの場合は`None`を返します。
+fn required(path: &[&str]) -> Option<Capability> {
+    let is_update = path.get(2) == Some(&"update");
+    Some(match path[0] {
+        "setting" if is_update => Capability::SettingWrite,
+        "setting" => Capability::SettingRead,
+        "wallpapers" if is_update || path[1] == "rename" => Capability::WallpapersWrite,
+        "wallpapers" => Capability::WallpapersRead,
+        "extensions" if is_update || path[1] == "reload" => Capability::ExtensionsWrite,
+        "extensions" => Capability::ExtensionsRead,
+        "templates" => Capability::TemplatesRead,
+        "gettext" => Capability::Gettext,
+        "open" => Capability::Dialog,
+        "openFolder" => Capability::OpenFolder,
+        "openWebsite" => Capability::OpenWebsite,
+        "getPath" => Capability::GetPath,
+        _ => return None,
+    })
+}
+
+/// 渡されたパスを正規化して、読み込みを許可するフォルダのリストにします。
+/// 存在しないフォルダは無視します。
+fn get_roots<'a, I: IntoIterator<Item = &'a str>>(paths: I) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter_map(|path| canonicalize(path).ok())
+        .collect()
+}
+
+/// ユーザーが読み込みを許可するファイルやフォルダとして使えるかどうかを調べます。
+/// 広すぎる場所を許可しないように、ファイルシステムのルートとホームフォルダ、それより上のフォルダは使えません。
+pub fn check_asset(path: &str) -> Result<(), String> {
+    let canonical = canonicalize(path).map_err(|_| t!("core.general.failedRead", path = path))?;
+    let home = var_os(if cfg!(target_os = "windows") {
+        "USERPROFILE"
+    } else {
+        "HOME"
+    })
+    .and_then(|x| canonicalize(x).ok());
+    if canonical.parent().is_none() || home.map_or(false, |x| x.starts_with(&canonical)) {
+        return Err(t!("core.permission.assetTooBroad", path = path));
+    };
+    Ok(())
+}
+
+/// ユーザーが許可したファイルやフォルダのうち、使えるものだけを取り出します。
+fn get_assets(assets: &[String]) -> impl Iterator<Item = &str> {
+    assets
+        .iter()
+        .map(|x| x.as_str())
+        .filter(|x| check_asset(x).is_ok())
+}
+
+/// WebViewごとの権限をまとめた構造体です。
+/// カスタムプロトコルのハンドラーに持たせておき、そのWebViewからのリクエストに付けて送ります。
+/// ページ側で`window.__WINDOW_ID__`を書き換えられても困らないように、ウィンドウIDもここで持ちます。
+#[derive(Clone, Debug)]
+pub struct Permission {
+    pub window_id: usize,
+    pub capabilities: Capabilities,
+    /// 自分自身の設定として扱う壁紙プロファイルの名前です。
+    pub wallpaper: Option<String>,
+    /// 自分自身の設定として扱う拡張機能の名前です。
+    pub extensions: Vec<String>,
+    /// ファイルの読み込みを許可するフォルダです。
+    pub roots: Vec<PathBuf>,
+}
+
+impl Permission {
+    /// 全ての権限を持つ設定画面用の権限を作ります。
+    /// ファイルは同梱のページと設定フォルダ、ユーザーが許可したフォルダの中のものを読み込めます。
+    pub fn full(window_id: usize, assets: &[String]) -> Self {
+        let setting = add_setting_path("").unwrap_or_default();
+        let pages = add_base("pages");
+        Self {
+            window_id: window_id,
+            capabilities: ALL.iter().copied().collect(),
+            wallpaper: None,
+            extensions: Vec::new(),
+            roots: get_roots(
+                [pages.as_str(), setting.as_str()]
+                    .into_iter()
+                    .chain(get_assets(assets)),
+            ),
+        }
+    }
+
+    /// 壁紙のWebView用の権限を作ります。
+    /// 拡張機能は壁紙と同じWebViewで動くので、読み込まれる拡張機能が要求した権限も追加されます。
+    /// ファイルは同梱のページと壁紙プロファイル、拡張機能とユーザーが許可したフォルダの中のものを読み込めます。
+    pub fn wallpaper(
+        window_id: usize,
+        wallpaper: &Wallpaper,
+        extensions: &[Extension],
+        assets: &[String],
+    ) -> Self {
+        let mut capabilities: Capabilities = WALLPAPER.iter().copied().collect();
+        for extension in extensions {
+            capabilities.extend(extension.detail.capabilities.iter().copied());
+        }
+        let pages = add_base("pages");
+        Self {
+            window_id: window_id,
+            capabilities: capabilities,
+            wallpaper: Some(wallpaper.name.clone()),
+            extensions: extensions.iter().map(|x| x.name.clone()).collect(),
+            roots: get_roots(
+                [pages.as_str(), wallpaper.path.as_str()]
+                    .into_iter()
+                    .chain(extensions.iter().map(|x| x.path.as_str()))
+                    .chain(get_assets(assets)),
+            ),
+        }
+    }
+
+    /// 全ての権限を持っているかどうかを調べます。
+    pub fn is_full(&self) -> bool {
+        ALL.iter().all(|x| self.capabilities.contains(x))
+    }
+
+    /// 自分自身の設定か保存領域へのリクエストかどうかを調べます。
+    /// 壁紙プロファイルは取得と書き込みのみで、削除は含めません。
+    /// 拡張機能も設定の取得と更新のみで、更新(インストール)と削除は含めません。
+    fn is_self(&self, path: &[&str]) -> bool {
+        if path.len() < 4 {
+            return false;
+        };
+        match (path[0], path[1]) {
+            ("wallpapers", "one") => {
+                self.wallpaper.as_deref() == Some(path[3])
+                    && (path[2] == "get" || path.get(4) == Some(&"write"))
+            }
+            // 更新と削除は自分自身でも許可しない。
+            ("extensions", "one") => {
+                path.len() == 4 && self.extensions.iter().any(|x| x == path[3])
+            }
+            ("storage", "wallpaper") => self.wallpaper.as_deref() == Some(path[3]),
+            ("storage", "extension") => self.extensions.iter().any(|x| x == path[3]),
+            _ => false,
+        }
+    }
+
+    /// リクエストが許可されているかどうかを調べます。
+    pub fn allows(&self, path: &[&str]) -> bool {
+        if self.capabilities.contains(&Capability::SelfSetting) && self.is_self(path) {
+            return true;
+        };
+        // フォルダの中身の一覧は、そのフォルダの読み込みが許可されているかどうかを処理する時に調べる。
+        if path[0] == "files" {
+            return true;
+        };
+        match required(path) {
+            Some(capability) => self.capabilities.contains(&capability),
+            _ => self.is_full(),
+        }
+    }
+
+    /// 渡されたファイルの読み込みが許可されているかどうかを調べます。
+    /// パスは`canonicalize`で正規化したものを渡してください。
+    pub fn can_read(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /// 読み込みが許可されたフォルダにあるファイルの一覧を取得します。
+    /// 壁紙の場合、相対パスはその壁紙プロファイルのフォルダからのパスとして扱います。
+    pub fn list_files(&self, folder: &str) -> Result<Vec<String>, String> {
+        let path = match &self.wallpaper {
+            Some(name) if Path::new(folder).is_relative() => PathBuf::from(add_setting_path(
+                &format!("wallpapers/{}/{}", name, folder),
+            )?),
+            _ => PathBuf::from(folder),
+        };
+        match canonicalize(&path) {
+            Ok(path) if self.can_read(&path) => {
+                list_files(&path).map_err(|_| t!("core.general.failedRead", path = folder))
+            }
+            _ => Err(t!("core.permission.denied", endpoint = folder)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir_all, write},
+        process,
+    };
+
+    use serde_json::from_str;
+
+    use super::*;
+
+    /// 渡された名前のファイルを入れたフォルダを一時フォルダに作ります。
+    fn make_folder(name: &str, files: &[&str]) -> PathBuf {
+        let folder = temp_dir().join(format!("freedomwall-{}-{}", name, process::id()));
+        let _ = remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        for file in files {
+            write(folder.join(file), "").unwrap();
+        }
+        folder
+    }
+
+    #[test]
+    fn list_files_in_approved_folder() {
+        let profile = make_folder("profile", &["index.html"]);
+        let pictures = make_folder("pictures", &["a.png", "b.jpg"]);
+        let other = make_folder("other", &["c.png"]);
+        let wallpaper = Wallpaper {
+            name: "Slideshow".to_string(),
+            path: profile.display().to_string(),
+            detail: from_str(r#"{"author": "", "description": "", "setting": {}}"#).unwrap(),
+        };
+        let pictures_path = pictures.display().to_string();
+
+        // 許可されていないフォルダの一覧は取得できない。
+        let permission = Permission::wallpaper(1, &wallpaper, &[], &[]);
+        assert!(permission.list_files(&pictures_path).is_err());
+
+        let permission = Permission::wallpaper(
+            1,
+            &wallpaper,
+            &[],
+            &[
+                pictures_path.clone(),
+                other.join("c.png").display().to_string(),
+            ],
+        );
+        let files = permission.list_files(&pictures_path).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .filter_map(|x| Path::new(x).file_name()?.to_str())
+            .collect();
+        assert_eq!(names, ["a.png", "b.jpg"]);
+        // 中のファイルだけが許可されたフォルダの一覧は取得できない。
+        assert!(permission.list_files(&other.display().to_string()).is_err());
+
+        for folder in [profile, pictures, other] {
+            let _ = remove_dir_all(folder);
+        }
+    }
+}
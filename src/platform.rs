@@ -1,12 +1,15 @@
 #[cfg(target_os = "macos")]
 pub mod macos;
 #[cfg(target_os = "macos")]
-pub use macos::{get_windows, Window};
+pub use macos::{get_process_path, get_windows, Window};
 
 #[cfg(target_os = "windows")]
 pub mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::{get_windows, Window};
+pub use windows::{get_process_path, get_windows, Window};
 
 pub mod all;
-pub use all::{ExtendedRects, Rects, Titles};
+pub use all::{
+    get_monitor, is_fullscreen, ExtendedRects, Processes, Rect, Rects, TargetState, Titles,
+    Visibilities, Visibility,
+};
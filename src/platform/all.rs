@@ -1,3 +1,137 @@
+use serde::Serialize;
+use wry::application::{event_loop::EventLoopWindowTarget, monitor::MonitorHandle};
+
 pub type Rects = [i32; 4];
 pub type ExtendedRects = Vec<(Rects, bool, isize)>;
 pub type Titles = Vec<String>;
+/// ウィンドウを持つプロセスの名前のリストです。
+pub type Processes = Vec<String>;
+/// ウィンドウの表示状態のリストです。
+pub type Visibilities = Vec<Visibility>;
+
+/// ウィンドウの表示状態です。
+/// `fullscreen`はモニターと比べる必要があるので`get_windows`では設定されず、マネージャーが設定します。
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Visibility {
+    /// 最小化されているかどうか (Windowsのみ)
+    /// Macでは別の操作スペースにあるウィンドウと区別できないので`other_workspace`になり、壁紙には渡されません。
+    #[cfg_attr(target_os = "macos", serde(skip_serializing))]
+    pub minimized: bool,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// 別の仮想デスクトップ (Macの場合は別の操作スペース) にあるかどうか
+    pub other_workspace: bool,
+    /// 他の理由でOSに隠されているかどうか (Windowsのみ)
+    pub cloaked: bool,
+}
+
+impl Visibility {
+    /// 画面上に実際に表示されているかどうかを調べます。
+    pub fn is_visible(&self) -> bool {
+        !self.minimized && !self.other_workspace && !self.cloaked
+    }
+}
+
+/// 画面上の位置とサイズです。
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    /// `get_windows`で取得した`Rects`から作ります。
+    /// Windowsの場合は左上と右下の位置で、Macの場合は幅、高さ、x、yの順番です。
+    pub fn from_rects(rect: &Rects) -> Self {
+        #[cfg(target_os = "windows")]
+        return Self {
+            x: rect[0],
+            y: rect[1],
+            width: rect[2] - rect[0],
+            height: rect[3] - rect[1],
+        };
+        #[cfg(target_os = "macos")]
+        return Self {
+            x: rect[2],
+            y: rect[3],
+            width: rect[0],
+            height: rect[1],
+        };
+    }
+}
+
+/// モニターの情報です。
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Monitor {
+    /// 何番目のモニターか
+    pub index: usize,
+    pub name: Option<String>,
+}
+
+/// 背景を付けるウィンドウの状態です。
+/// 壁紙のページには`window.freedomWall.target`として渡されます。
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetState {
+    pub title: String,
+    /// ウィンドウを持つプロセスの名前
+    pub process: String,
+    /// 一番前のウィンドウかどうか
+    pub focused: bool,
+    pub rect: Rect,
+    /// ウィンドウの中心があるモニター
+    pub monitor: Option<Monitor>,
+    /// 前から何番目のウィンドウか (0が一番前)
+    pub z_order: usize,
+    pub visibility: Visibility,
+}
+
+/// 渡されたモニターの位置とサイズを、`Rect`と同じ座標にして取得します。
+fn get_monitor_rect(monitor: &MonitorHandle) -> (f64, f64, f64, f64) {
+    let scale = if cfg!(target_os = "macos") {
+        monitor.scale_factor()
+    } else {
+        1.0
+    };
+    let (position, size) = (monitor.position(), monitor.size());
+    (
+        position.x as f64 / scale,
+        position.y as f64 / scale,
+        size.width as f64 / scale,
+        size.height as f64 / scale,
+    )
+}
+
+/// ウィンドウの中心があるモニターを取得します。
+/// Macの場合は`Rect`が論理座標なので、モニターの位置とサイズも論理座標にして比べます。
+pub fn get_monitor<T>(event_loop: &EventLoopWindowTarget<T>, rect: &Rect) -> Option<Monitor> {
+    let (x, y) = (
+        (rect.x + rect.width / 2) as f64,
+        (rect.y + rect.height / 2) as f64,
+    );
+    event_loop
+        .available_monitors()
+        .enumerate()
+        .find(|(_, monitor)| {
+            let (left, top, width, height) = get_monitor_rect(monitor);
+            left <= x && x < left + width && top <= y && y < top + height
+        })
+        .map(|(index, monitor)| Monitor {
+            index: index,
+            name: monitor.name(),
+        })
+}
+
+/// ウィンドウがいずれかのモニター全体を覆っているかどうかを調べます。
+pub fn is_fullscreen<T>(event_loop: &EventLoopWindowTarget<T>, rect: &Rect) -> bool {
+    event_loop.available_monitors().any(|monitor| {
+        let (left, top, width, height) = get_monitor_rect(&monitor);
+        rect.x as f64 <= left
+            && rect.y as f64 <= top
+            && left + width <= (rect.x + rect.width) as f64
+            && top + height <= (rect.y + rect.height) as f64
+    })
+}
@@ -1,7 +1,9 @@
 use std::{
     ffi::{c_void, CString},
     mem::transmute,
+    path::PathBuf,
     ptr::null,
+    time::Instant,
 };
 
 use wry::{
@@ -20,7 +22,7 @@ use objc::{
 
 use core_foundation::{
     array::{CFArrayGetCount, CFArrayGetValueAtIndex},
-    base::CFIndex,
+    base::{CFIndex, CFRelease},
     bundle::CFBundle,
     dictionary::{CFDictionaryGetValueIfPresent, CFDictionaryRef},
     number::{kCFNumberIntType, CFNumberGetValue, CFNumberRef},
@@ -29,13 +31,14 @@ use core_foundation::{
     },
 };
 use core_graphics::display::{
-    kCGNullWindowID, kCGWindowListExcludeDesktopElements, kCGWindowListOptionOnScreenOnly,
-    CGWindowListCopyWindowInfo,
+    kCGNullWindowID, kCGWindowListExcludeDesktopElements, kCGWindowListOptionAll,
+    kCGWindowListOptionOnScreenOnly, CGWindowListCopyWindowInfo,
 };
 
 use super::super::{
     data_manager::{Shift, Wallpaper},
-    platform::{ExtendedRects, Titles},
+    platform::{ExtendedRects, Processes, TargetState, Titles, Visibilities, Visibility},
+    render::SharedContext,
     window::WindowTrait,
 };
 
@@ -55,6 +58,21 @@ pub struct Window {
     pub target: String,
     before_front: bool,
     pub id: usize,
+    pub state: Option<TargetState>,
+    /// 読み込んでいる拡張機能の名前
+    pub extensions: Vec<String>,
+    /// この背景ウィンドウを作った壁紙設定のID
+    pub rule: String,
+    /// テンプレートエンジンを使う壁紙プロファイルの場合のレンダリング用のデータ
+    pub render: Option<SharedContext>,
+    /// プレイリストの切り替えでフェードアウトを始めた時刻
+    pub leaving: Option<Instant>,
+    /// 現在の透明度
+    pub alpha: f64,
+    /// 透明度を変えている途中の場合の、変える前と後の透明度と始めた時刻
+    pub fading: Option<(f64, f64, Instant)>,
+    /// ページを一時停止しているかどうか
+    pub paused: bool,
 }
 
 /// 渡された&strのCStringを作る。
@@ -111,6 +129,21 @@ fn get_cfnumber(number: *const c_void) -> Option<i32> {
 extern "C" {
     /// 渡された文字列から指定された位置にある文字を取り出します。
     fn CFStringGetCharacterAtIndex(theString: CFStringRef, idx: CFIndex) -> UniChar;
+    /// 渡されたPIDのプロセスの実行ファイルのパスを取得します。
+    fn proc_pidpath(pid: i32, buffer: *mut c_void, buffersize: u32) -> i32;
+}
+
+/// 渡されたPIDの動いているプロセスの実行ファイルのパスを取得します。
+/// プロセスがない場合は`None`を返します。
+pub fn get_process_path(pid: u32) -> Option<PathBuf> {
+    let mut raw = [0u8; 4096];
+    let length = unsafe { proc_pidpath(pid as i32, raw.as_mut_ptr() as _, raw.len() as u32) };
+    if length <= 0 {
+        return None;
+    };
+    Some(PathBuf::from(
+        String::from_utf8_lossy(&raw[..length as usize]).to_string(),
+    ))
 }
 
 /// CFStringをStringにします。
@@ -124,8 +157,14 @@ fn cfstring2string(text: CFStringRef) -> String {
 
 /// 存在する全てのウィンドウのタイトルや位置そしてサイズ等を取得します。
 /// 二番目のVectorの三番目のisizeの値はウィンドウ番号です。(背景ウィンドウの順序変更に使用する)
-pub fn get_windows() -> (Titles, ExtendedRects) {
+/// 三番目のVectorはプロセスの名前ですが、Macの場合はタイトルもアプリ名なので同じものになります。
+/// 四番目のVectorはウィンドウの表示状態です。
+/// 画面上にウィンドウが一つもないアプリは、最小化か別の操作スペースにあるとして後ろに追加します。
+/// この二つは区別できないので、どちらも`other_workspace`とします。
+pub fn get_windows() -> (Titles, ExtendedRects, Processes, Visibilities) {
     let (mut windows_name, mut windows_rect): (_, ExtendedRects) = (Vec::new(), Vec::new());
+    let mut windows_process: Processes = Vec::new();
+    let mut windows_visibility: Visibilities = Vec::new();
 
     let windows = unsafe {
         CGWindowListCopyWindowInfo(
@@ -199,6 +238,8 @@ pub fn get_windows() -> (Titles, ExtendedRects) {
                 if same_before {
                     windows_name.pop();
                     windows_rect.pop();
+                    windows_process.pop();
+                    windows_visibility.pop();
                 };
                 windows_rect.push((
                     tentative,
@@ -213,11 +254,71 @@ pub fn get_windows() -> (Titles, ExtendedRects) {
                 if next_main {
                     next_main = false;
                 };
+                windows_process.push(title.clone());
                 windows_name.push(title);
+                windows_visibility.push(Visibility::default());
             };
         };
     }
-    (windows_name, windows_rect)
+    unsafe { CFRelease(windows as _) };
+
+    // 画面上にないウィンドウを取得する。
+    let windows = unsafe {
+        CGWindowListCopyWindowInfo(
+            kCGWindowListOptionAll | kCGWindowListExcludeDesktopElements,
+            kCGNullWindowID,
+        )
+    };
+    for index in 0..unsafe { CFArrayGetCount(windows) } {
+        let data = unsafe { CFArrayGetValueAtIndex(windows, index) as CFDictionaryRef };
+
+        let title = cfstring2string(
+            match get_cfdictionary_value_from_str(data, "kCGWindowOwnerName") {
+                Some(value) => value as CFStringRef,
+                _ => continue,
+            },
+        );
+        if title.is_empty()
+            || windows_name.contains(&title)
+            || get_cfdictionary_value_from_str(data, "kCGWindowLayer")
+                .and_then(get_cfnumber)
+                .unwrap_or(-1)
+                != 0
+        {
+            continue;
+        };
+        let rect = match get_cfdictionary_value_from_str(data, "kCGWindowBounds") {
+            Some(value) => value as CFDictionaryRef,
+            _ => continue,
+        };
+        let mut tentative = [0 as i32; 4];
+        for (i, key) in ["Width", "Height", "X", "Y"].iter().enumerate() {
+            tentative[i] = get_cfdictionary_value_from_str(rect, key)
+                .and_then(get_cfnumber)
+                .unwrap_or(0);
+        }
+        windows_rect.push((
+            tentative,
+            false,
+            get_cfdictionary_value_from_str(data, "kCGWindowNumber")
+                .and_then(get_cfnumber)
+                .unwrap_or(0) as isize,
+        ));
+        windows_process.push(title.clone());
+        windows_name.push(title);
+        windows_visibility.push(Visibility {
+            other_workspace: true,
+            ..Default::default()
+        });
+    }
+    unsafe { CFRelease(windows as _) };
+
+    (
+        windows_name,
+        windows_rect,
+        windows_process,
+        windows_visibility,
+    )
 }
 
 impl WindowTrait for Window {
@@ -230,6 +331,14 @@ impl WindowTrait for Window {
             wallpaper: wallpaper,
             target: target,
             before_front: false,
+            state: None,
+            extensions: Vec::new(),
+            rule: String::new(),
+            render: None,
+            leaving: None,
+            alpha: 1.0,
+            fading: None,
+            paused: false,
         };
         window
     }
@@ -1,25 +1,80 @@
-use std::mem::size_of;
+use std::{mem::size_of, path::PathBuf, time::Instant};
 
 use wry::{application::platform::windows::WindowExtWindows, webview::WebView};
 
 use windows_sys::Win32::{
-    Foundation::{BOOL, HWND, LPARAM, RECT},
-    Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS},
+    Foundation::{CloseHandle, BOOL, HWND, LPARAM, RECT, STILL_ACTIVE},
+    Graphics::Dwm::{
+        DwmGetWindowAttribute, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS, DWM_CLOAKED_SHELL,
+    },
+    System::Threading::{
+        GetExitCodeProcess, OpenProcess, QueryFullProcessImageNameW,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    },
     UI::WindowsAndMessaging::{
-        EnumWindows, GetForegroundWindow, GetWindowTextW, MoveWindow, SetLayeredWindowAttributes,
-        SetWindowLongA, SetWindowPos,
+        EnumWindows, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+        IsWindowVisible, IsZoomed, MoveWindow, SetLayeredWindowAttributes, SetWindowLongA,
+        SetWindowPos,
     },
 };
 
 use super::super::{
     data_manager::{Shift, Wallpaper},
-    platform::{ExtendedRects, Titles},
+    platform::{ExtendedRects, Processes, TargetState, Titles, Visibilities, Visibility},
+    render::SharedContext,
     window::WindowTrait,
 };
 
-static mut DATA: (Titles, ExtendedRects) = (Vec::new(), Vec::new());
+static mut DATA: (Titles, ExtendedRects, Processes, Visibilities) =
+    (Vec::new(), Vec::new(), Vec::new(), Vec::new());
 static mut BEFORE: HWND = 0;
 
+/// ウィンドウを持つプロセスの実行ファイルの名前を取得します。
+/// 取得できなかった場合は空の文字列を返します。
+unsafe fn get_process_name(hwnd: HWND) -> String {
+    let mut id = 0;
+    GetWindowThreadProcessId(hwnd, &mut id);
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, id);
+    if process == 0 {
+        return String::new();
+    };
+    let mut raw: [u16; 1024] = [0; 1024];
+    let mut length = 1024;
+    let result = QueryFullProcessImageNameW(process, 0, &mut raw as _, &mut length);
+    CloseHandle(process);
+    if result == 0 {
+        return String::new();
+    };
+    String::from_utf16_lossy(&raw[..length as usize])
+        .rsplit('\\')
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+/// 渡されたPIDの動いているプロセスの実行ファイルのパスを取得します。
+/// プロセスがない場合は`None`を返します。
+pub fn get_process_path(pid: u32) -> Option<PathBuf> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process == 0 {
+            return None;
+        };
+        let mut code = 0;
+        let alive = GetExitCodeProcess(process, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        let mut raw: [u16; 1024] = [0; 1024];
+        let mut length = 1024;
+        let result = QueryFullProcessImageNameW(process, 0, &mut raw as _, &mut length);
+        CloseHandle(process);
+        if !alive || result == 0 {
+            return None;
+        };
+        Some(PathBuf::from(String::from_utf16_lossy(
+            &raw[..length as usize],
+        )))
+    }
+}
+
 /// `get_windows`内の`EnumWindows`に渡す関数です。
 unsafe extern "system" fn lpenumfunc(hwnd: HWND, _: LPARAM) -> BOOL {
     // ウィンドウのタイトルを取得する。
@@ -27,6 +82,12 @@ unsafe extern "system" fn lpenumfunc(hwnd: HWND, _: LPARAM) -> BOOL {
     let length = GetWindowTextW(hwnd, &mut raw as _, 512);
     DATA.0
         .push(String::from_utf16_lossy(&raw[..length as usize]).to_string());
+    // プロセス名の取得は重いので、背景の対象になり得るウィンドウのみにする。
+    DATA.2.push(if length > 0 && IsWindowVisible(hwnd) != 0 {
+        get_process_name(hwnd)
+    } else {
+        String::new()
+    });
     // ウィンドウのサイズ等を取得する。
     let mut rect = RECT {
         left: 0,
@@ -46,6 +107,21 @@ unsafe extern "system" fn lpenumfunc(hwnd: HWND, _: LPARAM) -> BOOL {
         GetForegroundWindow() == hwnd,
         BEFORE,
     ));
+    // 最小化されているか、別の仮想デスクトップにある等で隠されているかを取得する。
+    let mut cloaked: u32 = 0;
+    DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_CLOAKED,
+        &mut cloaked as *mut u32 as *mut _,
+        size_of::<u32>() as u32,
+    );
+    DATA.3.push(Visibility {
+        minimized: IsIconic(hwnd) != 0,
+        maximized: IsZoomed(hwnd) != 0,
+        fullscreen: false,
+        other_workspace: cloaked & DWM_CLOAKED_SHELL != 0,
+        cloaked: cloaked != 0 && cloaked & DWM_CLOAKED_SHELL == 0,
+    });
     if hwnd != 0 {
         BEFORE = hwnd;
     };
@@ -56,9 +132,11 @@ unsafe extern "system" fn lpenumfunc(hwnd: HWND, _: LPARAM) -> BOOL {
 /// 全てのウィンドウのタイトルやサイズ等を取得します。
 /// 二番目のVectorの三番目のisizeの値は、そのウィンドウの前にあるウィンドウのHWNDです。
 /// 一番前のウィンドウの場合は前がいないので代わりに0となります。(背景ウィンドウの順序変更に使う)
-pub fn get_windows() -> (Titles, ExtendedRects) {
+/// 三番目のVectorはウィンドウを持つプロセスの実行ファイルの名前で、四番目はウィンドウの表示状態です。
+/// 最小化されたウィンドウや隠されたウィンドウの位置とサイズは正しくないので、表示状態を確認してから使ってください。
+pub fn get_windows() -> (Titles, ExtendedRects, Processes, Visibilities) {
     unsafe {
-        DATA = (Vec::new(), Vec::new());
+        DATA = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
         assert_eq!(EnumWindows(Some(lpenumfunc), 0), 1);
         BEFORE = 0;
         DATA.clone()
@@ -70,6 +148,21 @@ pub struct Window {
     pub wallpaper: Wallpaper,
     pub target: String,
     pub id: usize,
+    pub state: Option<TargetState>,
+    /// 読み込んでいる拡張機能の名前
+    pub extensions: Vec<String>,
+    /// この背景ウィンドウを作った壁紙設定のID
+    pub rule: String,
+    /// テンプレートエンジンを使う壁紙プロファイルの場合のレンダリング用のデータ
+    pub render: Option<SharedContext>,
+    /// プレイリストの切り替えでフェードアウトを始めた時刻
+    pub leaving: Option<Instant>,
+    /// 現在の透明度
+    pub alpha: f64,
+    /// 透明度を変えている途中の場合の、変える前と後の透明度と始めた時刻
+    pub fading: Option<(f64, f64, Instant)>,
+    /// ページを一時停止しているかどうか
+    pub paused: bool,
     front: bool,
     hwnd: HWND,
     first: bool,
@@ -90,6 +183,14 @@ impl WindowTrait for Window {
             id: id,
             wallpaper: data,
             target: target,
+            state: None,
+            extensions: Vec::new(),
+            rule: String::new(),
+            render: None,
+            leaving: None,
+            alpha: 1.0,
+            fading: None,
+            paused: false,
             front: false,
             first: true,
         };
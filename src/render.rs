@@ -0,0 +1,119 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env::consts::OS,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+use tera::{Context, Tera};
+use urlencoding::encode;
+
+use wry::{
+    http::{Response, ResponseBuilder},
+    Error,
+};
+
+use super::platform::TargetState;
+
+/// 壁紙のページのレンダリングに渡すデータです。
+/// テンプレートからは`{{ settings.imagePath }}`のように使えます。
+#[derive(Serialize, Clone)]
+pub struct RenderContext {
+    /// 壁紙プロファイルの名前
+    pub name: String,
+    /// 壁紙プロファイルの設定
+    pub settings: HashMap<String, Value>,
+    /// FreedomWallの言語設定 (`ja`や`en`)
+    pub locale: String,
+    /// 背景対象のウィンドウの状態
+    pub target: TargetState,
+    /// 動いているOS (`windows`か`macos`)
+    pub platform: &'static str,
+    /// レンダリングするファイルがある壁紙プロファイルのフォルダ
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// 背景ウィンドウと、そのWebViewのリクエストを処理するクロージャで共有するレンダリング用のデータです。
+pub type SharedContext = Rc<RefCell<RenderContext>>;
+
+impl RenderContext {
+    pub fn new(
+        name: String,
+        settings: HashMap<String, Value>,
+        locale: String,
+        target: TargetState,
+        root: PathBuf,
+    ) -> Self {
+        Self {
+            name: name,
+            settings: settings,
+            locale: locale,
+            target: target,
+            platform: OS,
+            root: root,
+        }
+    }
+
+    /// 渡されたファイルをレンダリングするべきかどうかを調べます。
+    /// 壁紙プロファイルのフォルダにあるHTMLファイルのみが対象です。
+    pub fn is_target(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+            && matches!(
+                path.extension().and_then(|x| x.to_str()),
+                Some("html") | Some("htm")
+            )
+    }
+
+    /// ファイルをテンプレートとしてレンダリングします。
+    pub fn render(&self, path: &Path) -> Result<String, String> {
+        let raw = read_to_string(path).map_err(|e| e.to_string())?;
+        let name = path
+            .strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+
+        let mut tera = Tera::default();
+        tera.add_raw_template(&name, &raw)
+            .map_err(|e| get_message(&e))?;
+        let context = Context::from_serialize(self).map_err(|e| get_message(&e))?;
+        tera.render(&name, &context).map_err(|e| get_message(&e))
+    }
+
+    /// ファイルをレンダリングしてレスポンスにします。
+    /// 失敗した場合はエラーの内容を付けてエラーページに移動させます。
+    pub fn render_response(&self, path: &Path) -> Result<Response, Error> {
+        match self.render(path) {
+            Ok(body) => ResponseBuilder::new()
+                .mimetype("text/html")
+                .status(200)
+                .body(body.into_bytes()),
+            Err(message) => {
+                println!("Render error ({}): {}", self.name, message);
+                ResponseBuilder::new()
+                    .header(
+                        "Location",
+                        format!("wry://pages/not_found.html?error={}", encode(&message)).as_str(),
+                    )
+                    .status(301)
+                    .body(Vec::with_capacity(0))
+            }
+        }
+    }
+}
+
+/// Teraのエラーを、原因まで含めた文字列にします。
+fn get_message(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message = format!("{}\n{}", message, error);
+        source = error.source();
+    }
+    message
+}
@@ -0,0 +1,295 @@
+use std::f64::consts::PI;
+
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// 日の出と日の入りの計算に使う位置です。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// 時刻の範囲です。
+/// 時刻は`"07:30"`のような形式か、`"sunrise"`や`"sunset-30"`のような日の出と日の入りからの分数で書きます。
+/// 終わりが始まりより前の場合は日付をまたぐ範囲になります。
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct TimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// 日付の範囲です。両端を含みます。
+/// 日付は毎年の`"12-01"`のような形式か、`"2024-12-01"`のような形式で書きます。
+/// 月日のみで終わりが始まりより前の場合は年をまたぐ範囲になります。
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct DateRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// 壁紙設定を使う時間の設定です。
+/// 指定された条件に全て当てはまる時のみ使われます。リストの中はどれか一つに当てはまれば良いです。
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Schedule {
+    #[serde(default)]
+    pub times: Vec<TimeRange>,
+    /// 曜日 (`0`が日曜日で`6`が土曜日)
+    #[serde(default)]
+    pub weekdays: Vec<u32>,
+    #[serde(default)]
+    pub dates: Vec<DateRange>,
+}
+
+/// スケジュールの判定に使う現在の日時です。
+/// ウィンドウごとに時刻を取得し直さないように、一度の処理で一回だけ作ります。
+pub struct Now {
+    year: i32,
+    month: u32,
+    day: u32,
+    weekday: u32,
+    /// 0時からの分数
+    minutes: f64,
+    /// 日の出と日の入りの0時からの分数
+    sun: (f64, f64),
+}
+
+impl Now {
+    /// 現在の日時を取得します。
+    /// 位置が設定されていない場合、日の出は6時で日の入りは18時とします。
+    pub fn get(location: Option<Location>) -> Self {
+        let now = Local::now();
+        let offset = now.offset().local_minus_utc() as f64 / 60.0;
+        Self {
+            year: now.year(),
+            month: now.month(),
+            day: now.day(),
+            weekday: now.weekday().num_days_from_sunday(),
+            minutes: (now.hour() * 60 + now.minute()) as f64 + now.second() as f64 / 60.0,
+            sun: match location {
+                Some(location) => get_sun(now.ordinal(), location, offset),
+                _ => (360.0, 1080.0),
+            },
+        }
+    }
+
+    /// 時刻の文字列を0時からの分数にします。読み込めない場合は`None`を返します。
+    fn parse_time(&self, raw: &str) -> Option<f64> {
+        let raw = raw.trim();
+        for (name, base) in [("sunrise", self.sun.0), ("sunset", self.sun.1)] {
+            if let Some(rest) = raw.strip_prefix(name) {
+                let rest = rest.trim().trim_start_matches('+');
+                return Some(if rest.is_empty() {
+                    base
+                } else {
+                    base + rest.parse::<f64>().ok()?
+                });
+            };
+        }
+        let (hour, minute) = raw.split_once(':')?;
+        Some((hour.parse::<u32>().ok()? * 60 + minute.parse::<u32>().ok()?) as f64)
+    }
+
+    /// 現在の時刻が範囲の中かどうかを調べます。
+    fn in_time(&self, range: &TimeRange) -> bool {
+        match (self.parse_time(&range.start), self.parse_time(&range.end)) {
+            // 白夜や極夜の場合のために、一日以上の差がある範囲は一日中か空とする。
+            (Some(start), Some(end)) if end - start >= 1440.0 => true,
+            (Some(start), Some(end)) if end - start <= -1440.0 => false,
+            (Some(start), Some(end)) => {
+                let (start, end) = (start.rem_euclid(1440.0), end.rem_euclid(1440.0));
+                if start <= end {
+                    start <= self.minutes && self.minutes < end
+                } else {
+                    start <= self.minutes || self.minutes < end
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// 現在の日付が範囲の中かどうかを調べます。
+    fn in_date(&self, range: &DateRange) -> bool {
+        let today = (self.year, self.month, self.day);
+        match (parse_date(&range.start), parse_date(&range.end)) {
+            (
+                Some((Some(start_year), start_month, start_day)),
+                Some((Some(end_year), end_month, end_day)),
+            ) => {
+                (start_year, start_month, start_day) <= today
+                    && today <= (end_year, end_month, end_day)
+            }
+            (Some((None, start_month, start_day)), Some((None, end_month, end_day))) => {
+                let (start, end, today) = (
+                    (start_month, start_day),
+                    (end_month, end_day),
+                    (self.month, self.day),
+                );
+                if start <= end {
+                    start <= today && today <= end
+                } else {
+                    start <= today || today <= end
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Schedule {
+    /// 現在がスケジュールの中かどうかを調べます。
+    pub fn is_active(&self, now: &Now) -> bool {
+        (self.times.is_empty() || self.times.iter().any(|x| now.in_time(x)))
+            && (self.weekdays.is_empty() || self.weekdays.contains(&now.weekday))
+            && (self.dates.is_empty() || self.dates.iter().any(|x| now.in_date(x)))
+    }
+}
+
+/// `MM-DD`か`YYYY-MM-DD`の日付を読み込みます。
+fn parse_date(raw: &str) -> Option<(Option<i32>, u32, u32)> {
+    let parts: Vec<&str> = raw.trim().split('-').collect();
+    match parts[..] {
+        [month, day] => Some((None, month.parse().ok()?, day.parse().ok()?)),
+        [year, month, day] => Some((
+            Some(year.parse().ok()?),
+            month.parse().ok()?,
+            day.parse().ok()?,
+        )),
+        _ => None,
+    }
+}
+
+/// 日の出と日の入りの時刻を、地方時の0時からの分数で計算します。
+/// NOAAの近似式を使うので、インターネットへの接続は必要ありません。
+/// 白夜の場合は日の入りを日の出の一日後に、極夜の場合は日の入りを日の出の一日前にします。
+fn get_sun(day_of_year: u32, location: Location, offset: f64) -> (f64, f64) {
+    let gamma = 2.0 * PI / 365.0 * (day_of_year as f64 - 1.0);
+    let equation = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+    let latitude = location.latitude.to_radians();
+    let cos_hour_angle = 90.833f64.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+
+    if cos_hour_angle < -1.0 {
+        return (0.0, 1440.0);
+    } else if cos_hour_angle > 1.0 {
+        return (720.0, -720.0);
+    };
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    let noon = 720.0 - 4.0 * location.longitude - equation + offset;
+    (noon - 4.0 * hour_angle, noon + 4.0 * hour_angle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024年6月15日(土)の指定された時刻の`Now`を作ります。日の出は6時で日の入りは18時です。
+    fn at(hour: u32, minute: u32) -> Now {
+        Now {
+            year: 2024,
+            month: 6,
+            day: 15,
+            weekday: 6,
+            minutes: (hour * 60 + minute) as f64,
+            sun: (360.0, 1080.0),
+        }
+    }
+
+    fn range(start: &str, end: &str) -> TimeRange {
+        TimeRange {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_time() {
+        let now = at(12, 0);
+        assert_eq!(now.parse_time("07:30"), Some(450.0));
+        assert_eq!(now.parse_time(" 23:05 "), Some(1385.0));
+        assert_eq!(now.parse_time("sunrise"), Some(360.0));
+        assert_eq!(now.parse_time("sunrise+15"), Some(375.0));
+        assert_eq!(now.parse_time("sunset-30"), Some(1050.0));
+        assert_eq!(now.parse_time("sunset - 30"), None);
+        assert_eq!(now.parse_time("7"), None);
+        assert_eq!(now.parse_time("noon"), None);
+    }
+
+    #[test]
+    fn in_time() {
+        let daytime = range("09:00", "17:00");
+        assert!(at(9, 0).in_time(&daytime));
+        assert!(!at(17, 0).in_time(&daytime));
+        assert!(!at(8, 59).in_time(&daytime));
+
+        // 日付をまたぐ範囲
+        let night = range("22:00", "06:00");
+        assert!(at(23, 0).in_time(&night));
+        assert!(at(3, 0).in_time(&night));
+        assert!(!at(12, 0).in_time(&night));
+
+        let evening = range("sunset-30", "sunrise");
+        assert!(at(17, 30).in_time(&evening));
+        assert!(at(5, 59).in_time(&evening));
+        assert!(!at(17, 29).in_time(&evening));
+
+        assert!(!at(12, 0).in_time(&range("12:00", "noon")));
+    }
+
+    #[test]
+    fn in_time_polar() {
+        // 白夜の場合は日の出から日の入りまでが一日中になる。
+        let mut now = at(2, 0);
+        now.sun = (0.0, 1440.0);
+        assert!(now.in_time(&range("sunrise", "sunset")));
+        // 極夜の場合は日の出から日の入りまでが空になり、日の入りから日の出までが一日中になる。
+        now.sun = (720.0, -720.0);
+        assert!(!now.in_time(&range("sunrise", "sunset")));
+        assert!(now.in_time(&range("sunset", "sunrise")));
+    }
+
+    #[test]
+    fn in_date() {
+        let now = at(12, 0);
+        let dates = |start: &str, end: &str| DateRange {
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        assert!(now.in_date(&dates("06-01", "06-15")));
+        assert!(!now.in_date(&dates("06-16", "06-30")));
+        // 年をまたぐ範囲
+        assert!(now.in_date(&dates("06-01", "01-31")));
+        assert!(!now.in_date(&dates("12-01", "01-31")));
+        assert!(now.in_date(&dates("2024-01-01", "2024-12-31")));
+        assert!(!now.in_date(&dates("2025-01-01", "2025-12-31")));
+    }
+
+    #[test]
+    fn get_sun() {
+        // 夏至の東京では日の出が4時25分ごろで日の入りが19時ごろになる。
+        let tokyo = Location {
+            latitude: 35.68,
+            longitude: 139.76,
+        };
+        let (sunrise, sunset) = super::get_sun(172, tokyo, 540.0);
+        assert!((sunrise - 265.0).abs() < 10.0, "sunrise: {}", sunrise);
+        assert!((sunset - 1140.0).abs() < 10.0, "sunset: {}", sunset);
+
+        // 北極圏では夏至の頃は白夜で冬至の頃は極夜になる。
+        let svalbard = Location {
+            latitude: 78.22,
+            longitude: 15.65,
+        };
+        assert_eq!(super::get_sun(172, svalbard, 60.0), (0.0, 1440.0));
+        assert_eq!(super::get_sun(355, svalbard, 60.0), (720.0, -720.0));
+    }
+}
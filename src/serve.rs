@@ -0,0 +1,291 @@
+use std::{
+    fs::{metadata, read, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use url::Url;
+use urlencoding::decode;
+
+use wry::{
+    http::{
+        header::{
+            ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+            IF_RANGE, LAST_MODIFIED, RANGE,
+        },
+        Request, Response, ResponseBuilder,
+    },
+    Error,
+};
+
+use super::data_manager::{add_base, add_setting_path};
+
+/// 範囲指定のリクエストに一度に返す最大のバイト数です。
+/// 動画等の大きなファイルを丸ごとメモリに読み込まないように、これを超える範囲は切り詰めて返します。
+/// 残りはWebView側が続きの範囲を改めてリクエストしてきます。
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// URLを実際のファイルのパスにします。
+/// `fw://`のURLは以下のように設定フォルダ等の場所に関係なく使える論理的なアドレスです。
+/// - `fw://app/<path>`: アプリに同梱されているファイル (例: `fw://app/pages/not_found.html`)
+/// - `fw://wallpaper/<name>/<file>`: 壁紙プロファイルのファイル
+/// - `fw://extension/<name>/<file>`: 拡張機能のファイル
+/// - `fw://file/<path>`: ユーザーが許可したフォルダにあるファイル (絶対パス)
+///
+/// `wry://`のURLは以前の形式で、絶対パスがそのまま入っています。
+/// Windowsの以前の壁紙は`https://wry.c//Users/...`のようにドライブ文字をホストにしたURLを使うので、`c:/Users/...`として扱います。
+pub fn url2path(url: &Url) -> Option<PathBuf> {
+    if url.scheme() != "fw" {
+        return if url.host_str() == Some("pages") {
+            Some(PathBuf::from(format!(
+                "{}/{}",
+                add_base("pages"),
+                url.path()
+            )))
+        } else if let Some(drive) = url.host_str().filter(|host| {
+            cfg!(target_os = "windows")
+                && host.len() == 1
+                && host.chars().all(|x| x.is_ascii_alphabetic())
+        }) {
+            Some(PathBuf::from(format!(
+                "{}:/{}",
+                drive,
+                decode(url.path()).ok()?.trim_start_matches('/')
+            )))
+        } else {
+            url.to_file_path().ok()
+        };
+    };
+
+    let path = decode(url.path()).ok()?;
+    let relative = path.trim_start_matches('/');
+    match url.host_str()? {
+        "app" => Some(PathBuf::from(add_base(relative))),
+        "wallpaper" => add_setting_path(&format!("wallpapers/{}", relative))
+            .ok()
+            .map(PathBuf::from),
+        "extension" => add_setting_path(&format!("extensions/{}", relative))
+            .ok()
+            .map(PathBuf::from),
+        "file" => Url::parse(&format!("file://{}", url.path()))
+            .ok()?
+            .to_file_path()
+            .ok(),
+        _ => None,
+    }
+}
+
+/// フォルダにあるファイルの絶対パスを名前順に並べて取得します。フォルダは含めません。
+pub fn list_files(folder: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in folder.read_dir()? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path.display().to_string());
+        };
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// UNIX時間からの経過秒数を取得します。
+fn get_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// 時刻をHTTPの日付の形式(`Thu, 01 Jan 1970 00:00:00 GMT`)の文字列にします。
+fn http_date(time: SystemTime) -> String {
+    let seconds = get_seconds(time);
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+
+    // 1970年1月1日からの日数を年月日にする。(グレゴリオ暦)
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// `Range`ヘッダーを解析して、返す範囲の最初と最後のバイトの位置を返します。
+/// 複数の範囲が指定された場合は最初の範囲のみを使います。
+/// 解析できない場合は`None`を、範囲がファイルの外の場合は`Some(Err(()))`を返します。
+fn parse_range(value: &str, length: u64) -> Option<Result<(u64, u64), ()>> {
+    let (start, end) = value
+        .trim()
+        .strip_prefix("bytes=")?
+        .split(',')
+        .next()?
+        .trim()
+        .split_once('-')?;
+
+    let (start, end) = if start.is_empty() {
+        // `bytes=-500`のような最後の500バイトの指定
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 {
+            return Some(Err(()));
+        };
+        (length.saturating_sub(suffix), length.saturating_sub(1))
+    } else {
+        let start: u64 = start.parse().ok()?;
+        (
+            start,
+            if end.is_empty() {
+                u64::MAX
+            } else {
+                end.parse().ok()?
+            },
+        )
+    };
+
+    if length == 0 || start >= length || start > end {
+        Some(Err(()))
+    } else {
+        Some(Ok((start, end.min(length - 1).min(start + CHUNK_SIZE - 1))))
+    }
+}
+
+/// ファイルをレスポンスにします。
+/// `Range`ヘッダーがある場合は指定された範囲のみを読み込んで206で返します。
+/// ない場合は`<img>`等が途中までしか表示されないことがないように、ファイル全体を200で返します。
+/// また、`ETag`と`Last-Modified`を付けて、条件付きリクエストには304で返します。
+pub fn file2response(request: &Request, path: &Path) -> Result<Response, Error> {
+    let data = metadata(path)?;
+    let length = data.len();
+    let modified = data.modified().ok();
+    let etag = format!(
+        "\"{:x}-{:x}\"",
+        length,
+        modified.map(get_seconds).unwrap_or(0)
+    );
+    let last_modified = modified.map(http_date);
+
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    let mut builder = ResponseBuilder::new()
+        .mimetype(&match mime_guess::from_path(path).first() {
+            Some(mime) => format!("{}/{}", mime.type_(), mime.subtype()),
+            _ => "text/plain".to_string(),
+        })
+        .header(ACCEPT_RANGES, "bytes")
+        .header(ETAG, etag.as_str());
+    if let Some(last_modified) = &last_modified {
+        builder = builder.header(LAST_MODIFIED, last_modified.as_str());
+    };
+
+    // 変更されていないのならファイルを読み込まずに返す。
+    if match header(IF_NONE_MATCH) {
+        Some(value) => value
+            .split(',')
+            .any(|x| x.trim() == etag || x.trim() == "*"),
+        _ => last_modified.is_some() && header(IF_MODIFIED_SINCE) == last_modified.as_deref(),
+    } {
+        return builder.status(304).body(Vec::with_capacity(0));
+    };
+
+    // `If-Range`が一致しない場合はファイルが変わっているので範囲指定を無視する。
+    let range = match header(IF_RANGE) {
+        Some(value) if value != etag && Some(value) != last_modified.as_deref() => None,
+        _ => header(RANGE).and_then(|value| parse_range(value, length)),
+    };
+
+    match range {
+        Some(Ok((start, end))) => {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(start))?;
+            let mut body = vec![0; (end - start + 1) as usize];
+            file.read_exact(&mut body)?;
+            builder
+                .status(206)
+                .header(
+                    CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, length).as_str(),
+                )
+                .header(CONTENT_LENGTH, body.len())
+                .body(body)
+        }
+        Some(Err(())) => builder
+            .status(416)
+            .header(CONTENT_RANGE, format!("bytes */{}", length).as_str())
+            .body(Vec::with_capacity(0)),
+        None => {
+            let body = read(path)?;
+            builder
+                .status(200)
+                .header(CONTENT_LENGTH, body.len())
+                .body(body)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(super::parse_range("bytes=0-499", 1000), Some(Ok((0, 499))));
+        assert_eq!(super::parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        // 最後のバイトより後の終わりはファイルの終わりまでにする。
+        assert_eq!(
+            super::parse_range("bytes=900-2000", 1000),
+            Some(Ok((900, 999)))
+        );
+        // 複数の範囲は最初のものだけを使う。
+        assert_eq!(
+            super::parse_range("bytes=0-9, 20-29", 1000),
+            Some(Ok((0, 9)))
+        );
+        // 一度に返すのは`CHUNK_SIZE`まで
+        assert_eq!(
+            super::parse_range("bytes=0-", CHUNK_SIZE * 2),
+            Some(Ok((0, CHUNK_SIZE - 1)))
+        );
+    }
+
+    #[test]
+    fn parse_suffix_range() {
+        assert_eq!(super::parse_range("bytes=-500", 1000), Some(Ok((500, 999))));
+        assert_eq!(super::parse_range("bytes=-2000", 1000), Some(Ok((0, 999))));
+        assert_eq!(super::parse_range("bytes=-0", 1000), Some(Err(())));
+    }
+
+    #[test]
+    fn parse_invalid_range() {
+        // ファイルの外や逆順の範囲は416にする。
+        assert_eq!(super::parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(super::parse_range("bytes=500-100", 1000), Some(Err(())));
+        assert_eq!(super::parse_range("bytes=0-", 0), Some(Err(())));
+        // 読み込めないものは範囲指定がないものとする。
+        assert_eq!(super::parse_range("items=0-10", 1000), None);
+        assert_eq!(super::parse_range("bytes=abc-10", 1000), None);
+        assert_eq!(super::parse_range("bytes=10", 1000), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, remove_file, rename, File},
+    io::Write,
+    path::Path,
+};
+
+use rust_i18n::t;
+use serde_json::{from_str, to_string};
+
+use super::data_manager::add_setting_path;
+
+/// 一つの保存領域に保存できるキーと値の合計の最大のバイト数です。
+pub const QUOTA: usize = 1024 * 1024;
+
+/// 壁紙プロファイルや拡張機能ごとの保存領域のデータです。
+pub type Store = BTreeMap<String, String>;
+
+/// 保存領域のファイルのパスを取得します。
+/// `kind`は`wallpaper`か`extension`で、保存領域は設定フォルダの`storage/wallpapers`等に作られます。
+fn get_path(kind: &str, name: &str) -> Result<String, String> {
+    let folder = match kind {
+        "wallpaper" => "wallpapers",
+        "extension" => "extensions",
+        _ => return Err("Not found".to_string()),
+    };
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(t!("core.storage.invalidName", name = name));
+    };
+    add_setting_path(&format!("storage/{}/{}.json", folder, name))
+}
+
+/// 保存領域のデータを全て読み込みます。
+/// まだ何も保存されていない場合は空のデータを返します。
+pub fn load(kind: &str, name: &str) -> Result<Store, String> {
+    let path = get_path(kind, name)?;
+    if !Path::new(&path).exists() {
+        return Ok(Store::new());
+    };
+    match read_to_string(&path).map(|raw| from_str::<Store>(&raw)) {
+        Ok(Ok(store)) => Ok(store),
+        _ => Err(t!("core.general.failedRead", path = &path)),
+    }
+}
+
+/// 保存領域のデータを書き込みます。
+/// 書き込み途中で終了しても壊れないように、一時ファイルに書き込んでから置き換えます。
+fn save(kind: &str, name: &str, store: &Store) -> Result<(), String> {
+    let path = get_path(kind, name)?;
+    let temporary = format!("{}.tmp", path);
+    let failed = || t!("core.general.failedWrite", path = &path);
+
+    if let Some(parent) = Path::new(&path).parent() {
+        create_dir_all(parent).map_err(|_| failed())?;
+    };
+    let mut file = File::create(&temporary).map_err(|_| failed())?;
+    if file
+        .write_all(to_string(store).unwrap().as_bytes())
+        .and_then(|_| file.sync_all())
+        .is_err()
+    {
+        let _ = remove_file(&temporary);
+        return Err(failed());
+    };
+    rename(&temporary, &path).map_err(|_| failed())
+}
+
+/// 保存領域で使われているバイト数を計算します。
+fn get_size(store: &Store) -> usize {
+    store
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum()
+}
+
+/// 値を取得します。
+pub fn get(kind: &str, name: &str, key: &str) -> Result<Option<String>, String> {
+    Ok(load(kind, name)?.remove(key))
+}
+
+/// 値を保存します。
+/// 保存後のデータが`QUOTA`を超える場合はエラーになります。
+pub fn set(kind: &str, name: &str, key: &str, value: String) -> Result<(), String> {
+    if key.is_empty() {
+        return Err(t!("core.storage.invalidName", name = key));
+    };
+    let mut store = load(kind, name)?;
+    store.insert(key.to_string(), value);
+    if get_size(&store) > QUOTA {
+        return Err(t!(
+            "core.storage.quotaExceeded",
+            name = name,
+            quota = &QUOTA.to_string()
+        ));
+    };
+    save(kind, name, &store)
+}
+
+/// 値を削除します。
+pub fn delete(kind: &str, name: &str, key: &str) -> Result<(), String> {
+    let mut store = load(kind, name)?;
+    if store.remove(key).is_some() {
+        save(kind, name, &store)?;
+    };
+    Ok(())
+}
+
+/// 保存領域を削除します。壁紙プロファイルや拡張機能を削除した時に使います。
+pub fn remove(kind: &str, name: &str) -> Result<(), String> {
+    let path = get_path(kind, name)?;
+    if Path::new(&path).exists() && remove_file(&path).is_err() {
+        return Err(t!("core.general.failedWrite", path = &path));
+    };
+    Ok(())
+}
+
+/// 保存領域のファイルを渡されたパスに移動します。壁紙プロファイルをゴミ箱に入れた時に使います。
+pub fn move_out(kind: &str, name: &str, to: &Path) -> Result<(), String> {
+    let path = get_path(kind, name)?;
+    if Path::new(&path).exists() && rename(&path, to).is_err() {
+        return Err(t!("core.general.renameFailed"));
+    };
+    Ok(())
+}
+
+/// 渡されたパスのファイルを保存領域に戻します。壁紙プロファイルをゴミ箱から戻した時に使います。
+pub fn move_in(kind: &str, name: &str, from: &Path) -> Result<(), String> {
+    let path = get_path(kind, name)?;
+    if from.exists() {
+        if let Some(parent) = Path::new(&path).parent() {
+            create_dir_all(parent).map_err(|e| e.to_string())?;
+        };
+        if rename(from, &path).is_err() {
+            return Err(t!("core.general.renameFailed"));
+        };
+    };
+    Ok(())
+}
+
+/// 保存領域の名前を変更します。壁紙プロファイルの名前を変更した時に使います。
+pub fn mv(kind: &str, before: &str, after: &str) -> Result<(), String> {
+    let (before, after) = (get_path(kind, before)?, get_path(kind, after)?);
+    if Path::new(&before).exists() && rename(&before, &after).is_err() {
+        return Err(t!("core.general.renameFailed"));
+    };
+    Ok(())
+}
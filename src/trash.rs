@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_dir, read_to_string, remove_dir_all, rename, write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use super::{
+    data_manager::{add_setting_path, Target, Wallpaper},
+    storage,
+};
+
+/// ゴミ箱にする設定フォルダの中のフォルダの名前です。
+pub const TRASH: &str = "trash";
+/// ゴミ箱に入れたものの情報を書き込むファイルの名前です。
+const INFO: &str = "trash.json";
+
+/// ゴミ箱に入れた壁紙プロファイルの情報です。
+/// ゴミ箱の中のフォルダには、この情報と壁紙プロファイルのフォルダ (`wallpaper`) と保存領域 (`storage.json`) が入ります。
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    /// ゴミ箱の中のフォルダの名前
+    #[serde(default)]
+    pub id: String,
+    /// 壁紙プロファイルの名前
+    pub name: String,
+    /// ゴミ箱に入れた時のUNIX時間の秒数
+    pub deleted_at: u64,
+    /// 一緒に外した壁紙設定 (シーンの名前と壁紙設定の一覧)
+    pub targets: HashMap<String, Vec<Target>>,
+}
+
+/// 現在のUNIX時間の秒数を取得します。
+fn get_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// ゴミ箱の中のフォルダのパスを取得します。
+fn get_folder(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err(t!("core.trash.notFound", id = id));
+    };
+    Ok(PathBuf::from(add_setting_path(TRASH)?).join(id))
+}
+
+/// ゴミ箱の中のものの情報を読み込みます。
+fn read(folder: &Path) -> Option<TrashItem> {
+    let mut item = from_str::<TrashItem>(&read_to_string(folder.join(INFO)).ok()?).ok()?;
+    item.id = folder.file_name()?.to_str()?.to_string();
+    Some(item)
+}
+
+/// 壁紙プロファイルをゴミ箱に入れます。
+/// 壁紙プロファイルのフォルダと保存領域を移動し、外した壁紙設定を一緒に書き込みます。
+pub fn put(wallpaper: &Wallpaper, targets: HashMap<String, Vec<Target>>) -> Result<(), String> {
+    let now = get_now();
+    let mut folder = get_folder(&format!("{}-{}", now, wallpaper.name))?;
+    // 同じ秒に同じ名前のものが入れられた場合に上書きしないようにする。
+    let mut count = 0;
+    while folder.exists() {
+        count += 1;
+        folder = get_folder(&format!("{}-{}-{}", now, count, wallpaper.name))?;
+    }
+    create_dir_all(&folder).map_err(|_| {
+        t!(
+            "core.general.mkdirFailed",
+            path = &folder.display().to_string()
+        )
+    })?;
+
+    let item = TrashItem {
+        id: String::new(),
+        name: wallpaper.name.clone(),
+        deleted_at: now,
+        targets: targets,
+    };
+    let info = folder.join(INFO);
+    write(&info, to_string_pretty(&item).unwrap()).map_err(|_| {
+        t!(
+            "core.general.failedWrite",
+            path = &info.display().to_string()
+        )
+    })?;
+    if rename(&wallpaper.path, folder.join("wallpaper")).is_err() {
+        let _ = remove_dir_all(&folder);
+        return Err(t!("core.general.renameFailed"));
+    };
+    // 保存領域を移動できなかった場合は、壁紙プロファイルを元の場所に戻す。
+    if let Err(message) =
+        storage::move_out("wallpaper", &wallpaper.name, &folder.join("storage.json"))
+    {
+        if rename(folder.join("wallpaper"), &wallpaper.path).is_ok() {
+            let _ = remove_dir_all(&folder);
+        };
+        return Err(message);
+    };
+    Ok(())
+}
+
+/// ゴミ箱の中のものを新しいものから順番に取得します。
+pub fn list() -> Result<Vec<TrashItem>, String> {
+    let root = PathBuf::from(add_setting_path(TRASH)?);
+    if !root.exists() {
+        return Ok(Vec::new());
+    };
+    let mut items: Vec<TrashItem> = read_dir(&root)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| read(&entry.ok()?.path()))
+        .collect();
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+/// ゴミ箱から壁紙プロファイルを戻します。同じ名前の壁紙プロファイルがある場合は戻せません。
+/// 外した壁紙設定を戻すのは呼び出し側で行ってください。
+pub fn restore(id: &str) -> Result<TrashItem, String> {
+    let folder = get_folder(id)?;
+    let item = read(&folder).ok_or_else(|| t!("core.trash.notFound", id = id))?;
+    let to = PathBuf::from(add_setting_path(&format!("wallpapers/{}", item.name))?);
+    if to.exists() {
+        return Err(t!("core.general.alreadyAdded", name = &item.name));
+    };
+    if rename(folder.join("wallpaper"), &to).is_err() {
+        return Err(t!("core.general.renameFailed"));
+    };
+    storage::move_in("wallpaper", &item.name, &folder.join("storage.json"))?;
+    remove(id)?;
+    Ok(item)
+}
+
+/// ゴミ箱の中のものを完全に消します。
+pub fn remove(id: &str) -> Result<(), String> {
+    let folder = get_folder(id)?;
+    if !folder.exists() {
+        return Err(t!("core.trash.notFound", id = id));
+    };
+    remove_dir_all(&folder)
+        .map_err(|e| format!("{}\nDetail: {}", t!("core.general.removeDirFailed"), e))
+}
+
+/// 渡された日数より前にゴミ箱に入れたものを完全に消します。`0`の場合は全て消します。
+pub fn empty(days: u64) -> Result<(), String> {
+    let now = get_now();
+    for item in list()? {
+        if now.saturating_sub(item.deleted_at) >= days * 86400 {
+            println!("Remove from trash: {}", item.id);
+            remove(&item.id)?;
+        };
+    }
+    Ok(())
+}
@@ -5,13 +5,19 @@ use rfd::{MessageButtons, MessageDialog, MessageLevel};
 use super::APPLICATION_NAME;
 
 /// ダイアログを表示します。
-pub fn dialog(message: &str, level: MessageLevel, button: MessageButtons) {
+/// ユーザーがOKかはいを押したかどうかを返します。
+pub fn dialog(message: &str, level: MessageLevel, button: MessageButtons) -> bool {
     MessageDialog::new()
         .set_title(APPLICATION_NAME)
         .set_description(message)
         .set_buttons(button)
         .set_level(level)
-        .show();
+        .show()
+}
+
+/// 確認のダイアログを表示して、ユーザーが了承したかどうかを返します。
+pub fn confirm(message: &str) -> bool {
+    dialog(message, MessageLevel::Warning, MessageButtons::OkCancel)
 }
 
 /// エラーを表示します。
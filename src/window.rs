@@ -1,9 +1,14 @@
+use std::{collections::HashMap, time::Instant};
+
+use serde_json::{to_string, Value};
+use url::{ParseError, Url};
+use urlencoding::encode;
 use wry::webview::WebView;
 
 pub use super::platform::Window;
 use super::{
     data_manager::{Shift, Wallpaper},
-    platform::Rects,
+    platform::{Rects, TargetState},
 };
 
 /// 背景ウィンドウの状態を変更したりするための構造体のトレイトです。
@@ -29,3 +34,110 @@ pub trait WindowTrait {
     /// クリックの貫通の有効/無効を設定します。
     fn set_click_through(&mut self, click_through: bool);
 }
+
+/// 背景対象のウィンドウの状態を更新します。
+/// 前回から変わっている場合のみ壁紙のページに送り、`freedomwall:targetchange`イベントを発生させます。
+pub fn update_state(window: &mut Window, state: TargetState) {
+    if window.state.as_ref() != Some(&state) {
+        let _ = window.webview.evaluate_script(&format!(
+            "window.__updateTarget__ && window.__updateTarget__({});",
+            to_string(&state).unwrap()
+        ));
+        // 次にレンダリングする時のために更新しておく。
+        if let Some(context) = &window.render {
+            context.borrow_mut().target = state.clone();
+        };
+        window.state = Some(state);
+    };
+}
+
+/// 壁紙プロファイルのページのURLを作ります。
+/// `query_parameters`が有効な以前の形式の壁紙プロファイルでは、設定をクエリパラメータにも入れます。
+pub fn get_wallpaper_url(wallpaper: &Wallpaper) -> Result<Url, ParseError> {
+    let url = format!("fw://wallpaper/{}/index.html", encode(&wallpaper.name));
+    if wallpaper.detail.query_parameters {
+        Url::parse_with_params(
+            &url,
+            wallpaper.detail.setting.iter().map(|(key, value)| {
+                (
+                    key,
+                    match value {
+                        Value::String(value) => value.clone(),
+                        _ => value.to_string(),
+                    },
+                )
+            }),
+        )
+    } else {
+        Url::parse(&url)
+    }
+}
+
+/// 壁紙プロファイルの設定を背景ウィンドウのページに送り、`freedomwall:settingchange`イベントを発生させます。
+pub fn update_setting(window: &mut Window, setting: HashMap<String, Value>) {
+    let _ = window.webview.evaluate_script(&format!(
+        "window.__updateSetting__('wallpaper', {}, {});",
+        to_string(&window.wallpaper.name).unwrap(),
+        to_string(&setting).unwrap()
+    ));
+    if let Some(context) = &window.render {
+        context.borrow_mut().settings = setting.clone();
+    };
+    window.wallpaper.detail.setting = setting;
+}
+
+/// 背景ウィンドウのページを一時停止または再開します。
+/// ページの動画と音声が止められ、`freedomwall:pausechange`イベントが発生します。
+pub fn set_paused(window: &mut Window, paused: bool) {
+    if window.paused != paused {
+        let _ = window.webview.evaluate_script(&format!(
+            "window.__setPaused__ && window.__setPaused__({});",
+            paused
+        ));
+        window.paused = paused;
+    };
+}
+
+/// 背景ウィンドウのページを指定された秒数でフェードアウトさせます。
+/// 何度も呼び出し、フェードアウトが終わった時に`true`を返します。
+pub fn fade_out(window: &mut Window, duration: f64) -> bool {
+    match window.leaving {
+        Some(start) => start.elapsed().as_secs_f64() >= duration,
+        None if duration > 0.0 => {
+            let _ = window.webview.evaluate_script(&format!(
+                "document.documentElement.style.transition = 'opacity {}s';
+                document.documentElement.style.opacity = '0';",
+                duration
+            ));
+            window.leaving = Some(Instant::now());
+            false
+        }
+        None => true,
+    }
+}
+
+/// 背景ウィンドウの透明度を、指定された秒数をかけて目標の透明度に近づけます。
+/// 目標が変わった場合はその時の透明度から変え始めます。`process_windows`から毎回呼び出してください。
+pub fn fade_alpha(window: &mut Window, goal: f64, duration: f64) {
+    if let Some((from, to, start)) = window.fading {
+        if to == goal {
+            let progress = (start.elapsed().as_secs_f64() / duration).min(1.0);
+            window.alpha = from + (to - from) * progress;
+            window.set_transparent(window.alpha);
+            if progress >= 1.0 {
+                window.fading = None;
+            };
+            return;
+        };
+    };
+
+    if window.alpha != goal {
+        if duration > 0.0 {
+            window.fading = Some((window.alpha, goal, Instant::now()));
+        } else {
+            window.fading = None;
+            window.alpha = goal;
+            window.set_transparent(goal);
+        };
+    };
+}